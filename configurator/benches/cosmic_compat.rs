@@ -0,0 +1,79 @@
+//! Load time, clone time and memory of the trees of the `cosmic_compat` schemas.
+//!
+//! Run with `cargo bench --bench cosmic_compat`.
+//!
+//! This is its own binary because it replaces the global allocator
+//! to count the allocated bytes.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    fs, hint,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use configurator::node::NodeContainer;
+use schemars::schema::RootSchema;
+
+const SCHEMAS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../cosmic_compat/schemas");
+
+const N: u32 = 100;
+
+struct CountingAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn main() {
+    let mut paths = fs::read_dir(SCHEMAS_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .collect::<Vec<_>>();
+    paths.sort();
+
+    for path in paths {
+        let schema: RootSchema = json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..N {
+            hint::black_box(NodeContainer::from_json_schema(&schema));
+        }
+        let load_time = start.elapsed() / N;
+
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let tree = NodeContainer::from_json_schema(&schema);
+        let memory = ALLOCATED.load(Ordering::Relaxed) - before;
+
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let clone = tree.clone();
+        let clone_memory = ALLOCATED.load(Ordering::Relaxed) - before;
+        drop(clone);
+
+        let start = Instant::now();
+        for _ in 0..N {
+            hint::black_box(tree.clone());
+        }
+        let clone_time = start.elapsed() / N;
+
+        println!(
+            "{}: load {load_time:?} ({} KiB), clone {clone_time:?} ({} KiB)",
+            path.file_name().unwrap().to_string_lossy(),
+            memory / 1024,
+            clone_memory / 1024,
+        );
+    }
+}
@@ -15,7 +15,7 @@ use crate::{
     config::Config,
     message::{AppMsg, ChangeMsg, PageMsg},
     node::{data_path::DataPathType, NumberValue},
-    page::{self, create_pages, Page},
+    page::{self, create_pages, Page, WriteMode},
     view::view_app,
 };
 
@@ -37,6 +37,7 @@ pub enum Dialog {
         data_path: Vec<DataPathType>,
         page_id: Entity,
     },
+    Error(String),
 }
 
 pub struct App {
@@ -44,6 +45,8 @@ pub struct App {
     pub nav_model: SingleSelectModel,
     pub config: ConfigManager<Config>,
     pub dialog: Option<Dialog>,
+    pub search: String,
+    pub search_all_pages: bool,
 }
 
 impl App {
@@ -99,6 +102,8 @@ impl cosmic::Application for App {
             nav_model,
             config,
             dialog: None,
+            search: String::new(),
+            search_all_pages: false,
         };
 
         (app, Task::none())
@@ -135,6 +140,32 @@ impl cosmic::Application for App {
                         page::Action::RemoveDialog => {
                             self.close_dialog();
                         }
+                        page::Action::WriteClipboard(content) => {
+                            return cosmic::iced::clipboard::write(content);
+                        }
+                        page::Action::SaveWriteMode(write_mode) => {
+                            let appid = page.appid.clone();
+
+                            self.config.update(|s| {
+                                s.overrides_only.retain(|a| a != &appid);
+
+                                if write_mode == WriteMode::Overrides {
+                                    s.overrides_only.push(appid);
+                                }
+                            });
+                        }
+                        page::Action::ReadClipboard(data_path) => {
+                            return cosmic::iced::clipboard::read().map(move |content| {
+                                let page_msg = match content {
+                                    Some(content) => PageMsg::ChangeMsg(
+                                        data_path.clone(),
+                                        ChangeMsg::Paste(content),
+                                    ),
+                                    None => PageMsg::None,
+                                };
+                                AppMsg::PageMsg(id, page_msg)
+                            });
+                        }
                     };
                 }
             }
@@ -167,7 +198,23 @@ impl cosmic::Application for App {
                 } => {
                     *name = input;
                 }
+                Dialog::Error(_) => {}
             },
+            AppMsg::Search(search) => {
+                self.search = search;
+            }
+            AppMsg::SearchAllPages(value) => {
+                self.search_all_pages = value;
+            }
+            AppMsg::OpenSearchResult(id, data_path) => {
+                self.search.clear();
+
+                if let Some(page) = self.nav_model.data_mut::<Page>(id) {
+                    page.data_path.set(data_path);
+                }
+
+                return self.on_nav_select(id);
+            }
         };
 
         // let a = self.nav_model.active_data::<Page>().unwrap();
@@ -212,6 +259,10 @@ impl cosmic::Application for App {
                 )))
                 .secondary_action(button::text("cancel").on_press(AppMsg::CloseDialog))
                 .into(),
+            Dialog::Error(error) => widget::dialog("Error")
+                .body(error)
+                .primary_action(button::text("ok").on_press(AppMsg::CloseDialog))
+                .into(),
         })
     }
 
@@ -0,0 +1,266 @@
+//! Timestamped copies of the configs, taken before they are overwritten.
+//!
+//! The backups of a page are in `$XDG_STATE_HOME/configurator/backups/<appid>/`,
+//! one entry per backup, named after its date. An entry is a copy of the config file,
+//! or of the config directory for the formats made of multiple files.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use chrono::{Local, NaiveDateTime};
+use xdg::BaseDirectories;
+
+use crate::providers::write_and_create_parent;
+
+/// Sorted like the dates, and safe in a file name
+const NAME_FORMAT: &str = "%Y-%m-%dT%H-%M-%S%.3f";
+
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub date: NaiveDateTime,
+}
+
+impl Backup {
+    pub fn date_string(&self) -> String {
+        self.date.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+pub fn backups_dir(appid: &str) -> anyhow::Result<PathBuf> {
+    let base_dirs = BaseDirectories::new()?;
+
+    Ok(base_dirs
+        .get_state_home()
+        .join("configurator/backups")
+        .join(appid))
+}
+
+/// Copy the config at `path` in `dir`, then only keep the `count` most recent backups.
+/// Nothing is done when the config doesn't exist yet.
+pub fn backup(dir: &Path, path: &Path, count: usize) -> anyhow::Result<()> {
+    if count == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    let mut backup_path = dir.join(Local::now().format(NAME_FORMAT).to_string());
+
+    // two backups in the same millisecond
+    while backup_path.exists() {
+        backup_path.as_mut_os_string().push("_");
+    }
+
+    info!("backup {} to {}", path.display(), backup_path.display());
+
+    if path.is_dir() {
+        // an empty config is a state that can be restored too
+        fs::create_dir_all(&backup_path)?;
+
+        for (name, content) in read_files(path)? {
+            write_and_create_parent(backup_path.join(name), content)?;
+        }
+    } else {
+        write_and_create_parent(&backup_path, fs::read(path)?)?;
+    }
+
+    for old in list(dir).into_iter().skip(count) {
+        debug!("remove the backup {}", old.path.display());
+
+        if old.path.is_dir() {
+            fs::remove_dir_all(&old.path)?;
+        } else {
+            fs::remove_file(&old.path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// The most recent first
+pub fn list(dir: &Path) -> Vec<Backup> {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut backups = read_dir
+        .flatten()
+        .filter_map(|dir_entry| {
+            let name = dir_entry.file_name();
+            let name = name.to_str()?.trim_end_matches('_');
+
+            Some(Backup {
+                path: dir_entry.path(),
+                date: NaiveDateTime::parse_from_str(name, NAME_FORMAT).ok()?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    backups.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| b.path.cmp(&a.path)));
+    backups
+}
+
+/// The files of a backup
+#[derive(Debug)]
+pub enum Content {
+    File(Vec<u8>),
+    Dir(Vec<(String, Vec<u8>)>),
+}
+
+/// Read the backup, so it can be restored even if it is removed in the meantime
+pub fn read(backup: &Backup) -> anyhow::Result<Content> {
+    if backup.path.is_dir() {
+        Ok(Content::Dir(read_files(&backup.path)?))
+    } else {
+        Ok(Content::File(fs::read(&backup.path)?))
+    }
+}
+
+/// Replace the config at `path` with the content of a backup.
+/// For a directory, the files that were not in the backup are removed.
+pub fn restore(content: Content, path: &Path) -> anyhow::Result<()> {
+    info!("restore {}", path.display());
+
+    match content {
+        Content::Dir(files) => {
+            if path.is_dir() {
+                for (name, _) in read_files(path)? {
+                    if !files.iter().any(|(backup_name, _)| backup_name == &name) {
+                        fs::remove_file(path.join(name))?;
+                    }
+                }
+            } else {
+                fs::create_dir_all(path)?;
+            }
+
+            for (name, content) in files {
+                write_and_create_parent(path.join(name), content)?;
+            }
+        }
+        Content::File(content) => write_and_create_parent(path, content)?,
+    }
+
+    Ok(())
+}
+
+/// The regular files of a directory. Other versions of a cosmic config are directories
+fn read_files(dir: &Path) -> anyhow::Result<Vec<(String, Vec<u8>)>> {
+    let mut files = Vec::new();
+
+    for dir_entry in fs::read_dir(dir)? {
+        let dir_entry = dir_entry?;
+
+        if !fs::metadata(dir_entry.path()).is_ok_and(|metadata| metadata.is_file()) {
+            continue;
+        }
+
+        let name = dir_entry
+            .file_name()
+            .into_string()
+            .map_err(|name| anyhow!("invalid file name: {name:?}"))?;
+
+        files.push((name, fs::read(dir_entry.path())?));
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use super::{backup, list, read, restore};
+
+    #[test]
+    fn test_backup_file() {
+        let root = Path::new("tests/backups/file");
+        let _ = fs::remove_dir_all(root);
+
+        let path = root.join("config.json");
+        let dir = root.join("backups");
+
+        // nothing to save yet
+        backup(&dir, &path, 2).unwrap();
+        assert!(list(&dir).is_empty());
+
+        for content in ["1", "2", "3"] {
+            fs::create_dir_all(root).unwrap();
+            fs::write(&path, content).unwrap();
+            backup(&dir, &path, 2).unwrap();
+        }
+
+        let backups = list(&dir);
+        assert_eq!(backups.len(), 2);
+        assert_eq!(fs::read_to_string(&backups[0].path).unwrap(), "3");
+
+        restore(read(&backups[1]).unwrap(), &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2");
+
+        // the temporary file of the atomic write is gone
+        assert_eq!(fs::read_dir(root).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_backup_dir() {
+        let root = Path::new("tests/backups/dir");
+        let _ = fs::remove_dir_all(root);
+
+        let path = root.join("config");
+        let dir = root.join("backups");
+
+        fs::create_dir_all(path.join("v1")).unwrap();
+        fs::write(path.join("a"), "1").unwrap();
+        backup(&dir, &path, 10).unwrap();
+
+        fs::write(path.join("a"), "2").unwrap();
+        fs::write(path.join("b"), "2").unwrap();
+
+        restore(read(&list(&dir)[0]).unwrap(), &path).unwrap();
+
+        assert_eq!(fs::read_to_string(path.join("a")).unwrap(), "1");
+        assert!(!path.join("b").exists());
+        assert!(path.join("v1").is_dir());
+    }
+
+    #[test]
+    fn test_backup_empty_dir() {
+        let root = Path::new("tests/backups/empty");
+        let _ = fs::remove_dir_all(root);
+
+        let path = root.join("config");
+        let dir = root.join("backups");
+
+        fs::create_dir_all(&path).unwrap();
+        backup(&dir, &path, 10).unwrap();
+        assert_eq!(list(&dir).len(), 1);
+
+        fs::write(path.join("a"), "1").unwrap();
+        restore(read(&list(&dir)[0]).unwrap(), &path).unwrap();
+
+        assert!(!path.join("a").exists());
+    }
+
+    #[test]
+    fn test_restore_pruned_backup() {
+        let root = Path::new("tests/backups/pruned");
+        let _ = fs::remove_dir_all(root);
+
+        let path = root.join("config.json");
+        let dir = root.join("backups");
+
+        fs::create_dir_all(root).unwrap();
+        fs::write(&path, "1").unwrap();
+        backup(&dir, &path, 1).unwrap();
+
+        fs::write(&path, "2").unwrap();
+        let content = read(&list(&dir)[0]).unwrap();
+
+        // the backup taken before restoring replaces the only one
+        backup(&dir, &path, 1).unwrap();
+        restore(content, &path).unwrap();
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1");
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +12,12 @@ pub struct Config {
     pub cosmic_compat: bool,
     /// masked appid
     pub masked: Vec<String>,
+    /// appid of the pages that only write the values that differ from the defaults
+    pub overrides_only: Vec<String>,
+    /// Number of backups kept per page. `0` disables them
+    pub backup_count: usize,
+    /// Number of backups kept for some pages, by appid. Replaces `backup_count`
+    pub backup_counts: HashMap<String, usize>,
 }
 
 impl Default for Config {
@@ -18,6 +26,9 @@ impl Default for Config {
             last_used_page: Default::default(),
             cosmic_compat: true,
             masked: vec![],
+            overrides_only: vec![],
+            backup_count: 10,
+            backup_counts: HashMap::new(),
         }
     }
 }
@@ -0,0 +1,21 @@
+//! The settings tree and the config formats, without the UI.
+//! Used by the app and the benches.
+
+#![feature(if_let_guard)]
+#![feature(let_chains)]
+
+#[allow(unused_imports)]
+#[macro_use]
+extern crate tracing;
+
+pub mod node;
+pub mod providers;
+pub mod utils;
+
+#[cfg(test)]
+mod json_schema_test_suite;
+#[cfg(test)]
+mod manual_testing;
+
+#[cfg(test)]
+mod test_common;
@@ -3,6 +3,7 @@
 #![feature(let_chains)]
 
 use app::App;
+use configurator::{node, providers, utils};
 use cosmic::app::Settings;
 
 #[allow(unused_imports)]
@@ -10,23 +11,14 @@ use cosmic::app::Settings;
 extern crate tracing;
 
 mod app;
+mod backup;
 mod config;
 mod localize;
 mod message;
-mod node;
 mod page;
-mod providers;
-mod utils;
 mod view;
 #[macro_use]
 mod icon;
-#[cfg(test)]
-mod json_schema_test_suite;
-#[cfg(test)]
-mod manual_testing;
-
-#[cfg(test)]
-mod test_common;
 
 fn setup_logs() {
     use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
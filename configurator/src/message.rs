@@ -1,6 +1,8 @@
+use std::path::PathBuf;
+
 use cosmic::widget::segmented_button::Entity;
 
-use crate::node::data_path::DataPathType;
+use crate::{node::data_path::DataPathType, page::WriteMode};
 
 #[derive(Clone, Debug)]
 pub enum AppMsg {
@@ -9,6 +11,9 @@ pub enum AppMsg {
     ReloadLocalConfig,
     CloseDialog,
     DialogInput(String),
+    Search(String),
+    SearchAllPages(bool),
+    OpenSearchResult(Entity, Vec<DataPathType>),
 }
 
 #[derive(Clone, Debug)]
@@ -18,6 +23,17 @@ pub enum PageMsg {
     ChangeMsg(Vec<DataPathType>, ChangeMsg),
     DialogAddNewNodeToObject(Vec<DataPathType>),
     DialogRenameKey(Vec<DataPathType>, String),
+    Copy(Vec<DataPathType>),
+    Paste(Vec<DataPathType>),
+    /// Start to drag the n-th value of the current node
+    DragStart(usize),
+    /// Drop the dragged value at the position n of the current node
+    Drop(usize),
+    /// The mouse was released, maybe outside of a drop target
+    DragEnd,
+    SetWriteMode(WriteMode),
+    /// Replace the config with the backup at this path
+    RestoreBackup(PathBuf),
     None,
 }
 
@@ -26,10 +42,28 @@ pub enum ChangeMsg {
     ApplyDefault,
     ChangeBool(bool),
     ChangeString(String),
+    ChangeChar(char),
     ChangeNumber(String),
+    /// Display the exact value of a float, or its shortest representation
+    NumberFullPrecision(bool),
+    ChangeAny(String),
     ChangeEnum(usize),
+    /// Enable or disable an `Option`
+    ChangeOption(bool),
     Remove(DataPathType),
     AddNewNodeToObject(String),
     AddNewNodeToArray,
-    RenameKey { prev: String, new: String },
+    RenameKey {
+        prev: String,
+        new: String,
+    },
+    Paste(String),
+    /// Remove a key that is not in the schema, or all of them
+    RemoveUnknown(Option<String>),
+    /// Move the value at position `from` to position `to`
+    Move {
+        from: usize,
+        to: usize,
+    },
+    Duplicate(DataPathType),
 }
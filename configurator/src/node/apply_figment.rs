@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, sync::Arc};
 
 use anyhow::{anyhow, bail};
 use figment::{
@@ -7,9 +7,12 @@ use figment::{
 };
 use indexmap::map::MutableKeys;
 
-use crate::utils::{data_default_profile_figment, json_value_eq_figment_value};
+use crate::{
+    providers::ron_value::RonValue,
+    utils::{data_default_profile_figment, json_value_eq_figment_value},
+};
 
-use super::{Node, NodeContainer};
+use super::{Node, NodeArrayTemplate, NodeContainer, NodeEnum};
 
 impl NodeContainer {
     // todo: use figment Value instead
@@ -20,6 +23,81 @@ impl NodeContainer {
         }
     }
 
+    /// Like [`Self::apply_figment`], but only touch the nodes whose value changed
+    pub fn update_figment(&mut self, figment: &Figment) -> anyhow::Result<()> {
+        match data_default_profile_figment(figment) {
+            Some(data) => self.update_value(Value::Dict(Tag::Default, data), true),
+            None => self.update_value(Value::Dict(Tag::Default, BTreeMap::new()), false),
+        }
+    }
+
+    /// Same result as [`Self::remove_value_rec`] followed by [`Self::apply_value`],
+    /// but the nodes that already hold their new value are left alone.
+    /// This keeps the state of the UI, like the variants of an enum
+    /// or a number being typed.
+    pub fn update_value(&mut self, value: Value, modified: bool) -> anyhow::Result<()> {
+        self.modified = modified;
+
+        match (value, &mut self.node) {
+            (Value::Dict(tag, mut values), Node::Object(node_object)) => {
+                // map entries and localized keys
+                if node_object.template.is_some() || node_object.localized_keys {
+                    node_object
+                        .nodes
+                        .retain(|key, node| !node.removable || values.contains_key(key));
+                }
+
+                for (key, n) in &mut node_object.nodes {
+                    if let Some(value) = values.remove(key) {
+                        n.update_value(value, modified)?;
+                    } else if let Some(default) = &n.default {
+                        n.update_value(default.clone(), false)?;
+                    } else {
+                        n.remove_value_rec();
+                    }
+                }
+
+                if let Some(template) = node_object.template() {
+                    for (key, value) in values {
+                        let mut node_type = template.clone();
+                        node_type.apply_value(value, modified)?;
+                        node_object.nodes.insert(key, node_type);
+                    }
+                } else {
+                    node_object.unknown = node_object.apply_localized(values, modified);
+                }
+            }
+            (value, Node::Enum(node_enum)) => {
+                let pos = node_enum.variant_of(&value).ok_or_else(|| {
+                    anyhow!(
+                        "can't find a compatible enum variant for \n{value:#?}.\n{node_enum:#?}"
+                    )
+                })?;
+
+                node_enum.value = Some(pos);
+                node_enum.variant_mut(pos).update_value(value, modified)?;
+            }
+            (Value::Empty(tag, value), Node::Option(node_option)) => {
+                node_option.enabled = Some(false);
+            }
+            (value, Node::Option(node_option)) => {
+                node_option.enabled = Some(true);
+                node_option.inner.update_value(value, modified)?;
+            }
+            (value, node) => {
+                let mut new = self.clone();
+                new.remove_value_rec();
+                new.apply_value(value, modified)?;
+
+                if new.to_value_full(&Tag::Default) != self.to_value_full(&Tag::Default) {
+                    *self = new;
+                }
+            }
+        };
+
+        Ok(())
+    }
+
     // todo: the modified logic in the function seems wrong (i probably fixed it)
     // todo2: analyze the entire logic
     pub fn apply_value(&mut self, value: Value, modified: bool) -> anyhow::Result<()> {
@@ -30,35 +108,24 @@ impl NodeContainer {
             (Value::String(tag, value), Node::String(node_string)) => {
                 node_string.value = Some(value);
             }
-            (Value::Dict(tag, values), Node::Enum(node_enum)) => {
-                let pos = values
-                    .iter()
-                    .find_map(|(key, value)| {
-                        let key = Value::String(tag, key.clone());
-                        node_enum.nodes.iter().position(|e| e.is_matching(&key))
-                    })
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "can't find a compatible enum variant for dict \n{values:#?}.\n{node_enum:#?}"
-                        )
-                    })?;
-
-                node_enum.value = Some(pos);
-                node_enum.nodes[pos].apply_value(Value::Dict(tag, values), modified)?;
+            (Value::Char(tag, value), Node::Char(node_char)) => {
+                node_char.value = Some(value);
+            }
+            (Value::String(tag, value), Node::Char(node_char)) => {
+                node_char.value = Some(
+                    single_char(&value)
+                        .ok_or_else(|| anyhow!("expected a single character, found {value:?}"))?,
+                );
             }
             (value, Node::Enum(node_enum)) => {
-                let pos = node_enum
-                    .nodes
-                    .iter()
-                    .position(|e| e.is_matching(&value))
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "can't find a compatible enum variant for \n{value:#?}.\n{node_enum:#?}"
-                        )
-                    })?;
+                let pos = node_enum.variant_of(&value).ok_or_else(|| {
+                    anyhow!(
+                        "can't find a compatible enum variant for \n{value:#?}.\n{node_enum:#?}"
+                    )
+                })?;
 
                 node_enum.value = Some(pos);
-                node_enum.nodes[pos].apply_value(value, modified)?;
+                node_enum.variant_mut(pos).apply_value(value, modified)?;
             }
             (Value::String(tag, value), Node::Value(node_value)) => {
                 // pass
@@ -93,9 +160,14 @@ impl NodeContainer {
                         node_type.apply_value(value, modified)?;
                         node_object.nodes.insert(key, node_type);
                     }
+                } else {
+                    // keys that are not in the schema
+                    node_object.unknown = node_object.apply_localized(values, modified);
                 }
             }
             (Value::Array(tag, values), Node::Array(node_array)) => {
+                node_array.check_len(values.len())?;
+
                 let mut nodes = Vec::new();
 
                 for (pos, value) in values.into_iter().enumerate() {
@@ -107,12 +179,60 @@ impl NodeContainer {
                 node_array.values = Some(nodes);
             }
             (Value::Empty(tag, value), Node::Null) => {}
+            (Value::Empty(tag, value), Node::Option(node_option)) => {
+                node_option.enabled = Some(false);
+            }
+            (value, Node::Option(node_option)) => {
+                node_option.enabled = Some(true);
+                node_option.inner.apply_value(value, modified)?;
+            }
+            (value, Node::Any(node_any)) => node_any.set_value(value),
             (value, node) => bail!("no compatible node for value = \n{value:#?}. \n{node:#?}"),
         };
 
         Ok(())
     }
 
+    /// Remember how the unknown values were written in a RON file,
+    /// see [`super::NodeObject::unknown_ron`]. `value` is the value of this node in the file.
+    pub fn keep_unknown_ron(&mut self, mut value: &RonValue) {
+        while let RonValue::Option(Some(inner)) | RonValue::Newtype(inner) = value {
+            value = inner;
+        }
+
+        match (&mut self.node, value) {
+            (Node::Option(node_option), value) => node_option.inner.keep_unknown_ron(value),
+            (Node::Object(node_object), value) => {
+                for (key, node) in &mut node_object.nodes {
+                    if let Some(value) = value.get(key) {
+                        node.keep_unknown_ron(value);
+                    }
+                }
+
+                for key in node_object.unknown.keys() {
+                    if let Some(value) = value.get(key) {
+                        node_object.unknown_ron.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            (Node::Enum(node_enum), value) => {
+                if let Some(pos) = node_enum.value
+                    && matches!(node_enum.nodes[pos].node, Node::Object(_))
+                {
+                    node_enum.variant_mut(pos).keep_unknown_ron(value);
+                }
+            }
+            (Node::Array(node_array), RonValue::List(values) | RonValue::Tuple(values)) => {
+                if let Some(nodes) = &mut node_array.values {
+                    for (node, value) in nodes.iter_mut().zip(values) {
+                        node.keep_unknown_ron(value);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
     pub fn remove_value_rec(&mut self) {
         match &mut self.node {
             Node::Null => {}
@@ -122,55 +242,193 @@ impl NodeContainer {
             Node::String(node_string) => {
                 node_string.value.take();
             }
+            Node::Char(node_char) => {
+                node_char.value.take();
+            }
             Node::Number(node_number) => {
                 node_number.value.take();
             }
+            Node::Option(node_option) => {
+                node_option.enabled.take();
+                node_option.inner.remove_value_rec();
+            }
             Node::Object(node_object) => {
                 // remove hashmap object ?
                 node_object
                     .nodes
                     .values_mut()
                     .for_each(|node| node.remove_value_rec());
+                node_object.unknown.clear();
+                node_object.unknown_ron.clear();
             }
             Node::Enum(node_enum) => {
                 node_enum.value.take();
+
+                for node in &mut node_enum.nodes {
+                    // don't copy variants that are shared and empty
+                    if node.modified || node.has_value() {
+                        Arc::make_mut(node).remove_value_rec();
+                    }
+                }
             }
             Node::Array(node_array) => {
                 // is it safe ?
                 node_array.values.take();
             }
             Node::Value(node_value) => {}
-            Node::Any => todo!(),
+            Node::Any(node_any) => {
+                node_any.value.take();
+                node_any.value_string.clear();
+                node_any.error.take();
+            }
         };
         self.modified = false;
     }
 
+    /// Give a value to a node that has none, from the defaults of the schema
+    pub fn init_with_defaults(&mut self, modified: bool) -> anyhow::Result<()> {
+        match (&self.default, &self.node) {
+            (Some(default), _) => self.apply_value(default.clone(), modified),
+            (None, Node::Object(_)) => {
+                self.apply_value(Value::Dict(Tag::Default, BTreeMap::new()), modified)
+            }
+            (None, Node::Array(node_array)) => {
+                let mut values = Vec::new();
+
+                // tuples and fixed-size arrays need all their values
+                for pos in 0..node_array.min.unwrap_or(0) as usize {
+                    let mut node = node_array.template(Some(pos));
+                    node.init_with_defaults(modified)?;
+                    node.modified = modified;
+                    values.push(node);
+                }
+
+                self.node.unwrap_array_mut().values = Some(values);
+                self.modified = modified;
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Select the variant `pos` of this enum node.
+    ///
+    /// A variant that never had a value is initialized with the default of the enum
+    /// if it describes this variant, or with its own defaults otherwise.
+    /// The values of the other variants are kept, so selecting them again restores them.
+    pub fn select_variant(&mut self, pos: usize) -> anyhow::Result<()> {
+        let Node::Enum(node_enum) = &mut self.node else {
+            bail!("not an enum: {:#?}", self.node);
+        };
+
+        let default = self
+            .default
+            .as_ref()
+            .filter(|default| node_enum.variant_of(default) == Some(pos));
+
+        let variant = node_enum.variant_mut(pos);
+
+        if !variant.has_value() {
+            match default {
+                Some(default) => variant.apply_value(default.clone(), true)?,
+                None => variant.init_with_defaults(true)?,
+            }
+        }
+
+        // the whole variant need to be written
+        variant.set_modified_rec();
+        node_enum.value = Some(pos);
+        self.modified = true;
+
+        Ok(())
+    }
+
+    /// Mark this node and all its children as modified
+    pub fn set_modified_rec(&mut self) {
+        self.modified = true;
+
+        match &mut self.node {
+            Node::Option(node_option) => node_option.inner.set_modified_rec(),
+            Node::Object(node_object) => node_object
+                .nodes
+                .values_mut()
+                .for_each(|node| node.set_modified_rec()),
+            Node::Enum(node_enum) => {
+                if let Some(pos) = node_enum.value {
+                    node_enum.variant_mut(pos).set_modified_rec();
+                }
+            }
+            Node::Array(node_array) => node_array
+                .values
+                .iter_mut()
+                .flatten()
+                .for_each(|node| node.set_modified_rec()),
+            _ => {}
+        }
+    }
+
     fn is_matching(&self, value: &Value) -> bool {
         // todo: should this match so many things ?
         // maybe only what is possible to put in an enum key
         // is it correct tho, maybe we should do a full equivalence on String
         match (value, &self.node) {
             (Value::String(tag, _), Node::String(node_string)) => true,
+            (Value::Char(tag, _), Node::Char(node_char)) => true,
+            (Value::String(tag, value), Node::Char(node_char)) => single_char(value).is_some(),
             (Value::String(tag, value), Node::Object(node_object)) => {
                 node_object.nodes.contains_key(value)
             }
             (Value::Bool(tag, _), Node::Bool(node_bool)) => true,
             (Value::Num(tag, num), Node::Number(node_number)) => true,
             (Value::Empty(tag, empty), Node::Null) => true,
+            (Value::Empty(tag, empty), Node::Option(node_option)) => true,
+            (value, Node::Option(node_option)) => node_option.inner.is_matching(value),
             (Value::Dict(tag, values), Node::Object(node_object)) => {
-                node_object.nodes.iter().all(|(key, n)| {
-                    let v = values.get(key).unwrap();
-                    n.is_matching(v)
-                })
+                // a missing field can have a default
+                node_object
+                    .nodes
+                    .iter()
+                    .all(|(key, n)| values.get(key).is_none_or(|v| n.is_matching(v)))
             }
             (Value::Array(tag, values), Node::Array(node_array)) => {
-                // todo: more complicated logic
-                true
+                node_array.check_len(values.len()).is_ok()
+                    && match &node_array.template {
+                        // todo: more complicated logic
+                        NodeArrayTemplate::All(_) => true,
+                        NodeArrayTemplate::FirstN(template) => template
+                            .iter()
+                            .zip(values)
+                            .all(|(node, value)| node.is_matching(value)),
+                    }
             }
             (value, Node::Value(node_value)) => {
                 json_value_eq_figment_value(&node_value.value, value)
             }
+            (value, Node::Any(node_any)) => true,
             _ => false,
         }
     }
 }
+
+impl NodeEnum {
+    /// Position of the variant compatible with `value`.
+    /// Dicts are matched by their key, like externally tagged enums.
+    pub(super) fn variant_of(&self, value: &Value) -> Option<usize> {
+        match value {
+            Value::Dict(tag, values) => values.keys().find_map(|key| {
+                let key = Value::String(*tag, key.clone());
+                self.nodes.iter().position(|e| e.is_matching(&key))
+            }),
+            value => self.nodes.iter().position(|e| e.is_matching(value)),
+        }
+    }
+}
+
+fn single_char(str: &str) -> Option<char> {
+    let mut chars = str.chars();
+
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
@@ -54,6 +54,12 @@ impl DataPath {
         self.pos.replace(next_pos);
     }
 
+    /// Replace the whole path, and select its last component
+    pub fn set(&mut self, vec: Vec<DataPathType>) {
+        self.pos = vec.len().checked_sub(1);
+        self.vec = vec;
+    }
+
     pub fn change_to(&mut self, pos: Option<usize>) {
         self.pos = pos;
     }
@@ -118,6 +124,12 @@ fn sanitize_path_rec<'a>(
                     None => Some(pos),
                 },
             },
+            Node::Option(node_option) => match component {
+                DataPathType::Indice(0) if node_option.enabled == Some(true) => {
+                    sanitize_path_rec(data_path, &node_option.inner, pos + 1)
+                }
+                _ => Some(pos),
+            },
             Node::Array(node_array) => match component {
                 DataPathType::Name(_) => Some(pos),
                 DataPathType::Indice(indice_data_path) => match &node_array.values {
@@ -154,6 +166,7 @@ impl NodeContainer {
 
                     node.get_at(data_path)
                 }
+                Node::Option(node_option) => node_option.inner.get_at(data_path),
                 Node::Array(node_array) => {
                     let p = component.unwrap_indice_ref();
                     let node = &node_array.values.as_ref().unwrap()[*p];
@@ -181,10 +194,11 @@ impl NodeContainer {
                 }
                 Node::Enum(node_enum) => {
                     let p = component.unwrap_indice_ref();
-                    let node = &mut node_enum.nodes[*p];
+                    let node = node_enum.variant_mut(*p);
 
                     node.get_at_mut(data_path)
                 }
+                Node::Option(node_option) => node_option.inner.get_at_mut(data_path),
                 Node::Array(node_array) => {
                     let p = component.unwrap_indice_ref();
                     let node = &mut node_array.values.as_mut().unwrap()[*p];
@@ -211,10 +225,11 @@ impl NodeContainer {
                 }
                 Node::Enum(node_enum) => {
                     let p = component.unwrap_indice_ref();
-                    let node = &mut node_enum.nodes[*p];
+                    let node = node_enum.variant_mut(*p);
 
                     node.set_modified(data_path);
                 }
+                Node::Option(node_option) => node_option.inner.set_modified(data_path),
                 Node::Array(node_array) => {
                     let p = component.unwrap_indice_ref();
                     let node = &mut node_array.values.as_mut().unwrap()[*p];
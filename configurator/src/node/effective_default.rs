@@ -0,0 +1,102 @@
+use figment::value::Value;
+
+use super::{data_path::DataPathType, Node, NodeContainer};
+
+/// Where the default of a node comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DefaultSource {
+    /// The default of the node itself. Its parent is in the config,
+    /// so the `Default` impl of the parent type don't apply.
+    Own,
+    /// The default of the ancestor whose data path has this length.
+    /// This ancestor is not in the config, so its default replaces the defaults of its children.
+    Ancestor(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EffectiveDefault<'a> {
+    pub value: &'a Value,
+    pub source: DefaultSource,
+}
+
+impl NodeContainer {
+    /// The default that applies at runtime to the node at `data_path`.
+    ///
+    /// With `#[serde(default)]`, a missing field takes its value from the default of its parent,
+    /// so the own default of a node only applies when its parent is in the config.
+    /// Otherwise, the default of the first missing ancestor is used, if it has one.
+    pub fn effective_default(&self, data_path: &[DataPathType]) -> Option<EffectiveDefault<'_>> {
+        let mut nodes = Vec::with_capacity(data_path.len());
+        let mut node = self;
+
+        for component in data_path {
+            node = node.child(component)?;
+            nodes.push(node);
+        }
+
+        // the root is the file itself, which is always there
+        let first_missing = nodes
+            .iter()
+            .position(|node| !node.modified)
+            .unwrap_or(nodes.len().saturating_sub(1));
+
+        for depth in first_missing..nodes.len() {
+            let Some(default) = &nodes[depth].default else {
+                continue;
+            };
+
+            let value = (depth + 1..nodes.len()).try_fold(default, |value, pos| {
+                nodes[pos - 1].child_value(value, &data_path[pos])
+            });
+
+            if let Some(value) = value {
+                let source = if depth + 1 == nodes.len() {
+                    DefaultSource::Own
+                } else {
+                    DefaultSource::Ancestor(depth + 1)
+                };
+
+                return Some(EffectiveDefault { value, source });
+            }
+        }
+
+        if nodes.is_empty() {
+            return self.default.as_ref().map(|value| EffectiveDefault {
+                value,
+                source: DefaultSource::Own,
+            });
+        }
+
+        None
+    }
+
+    fn child(&self, component: &DataPathType) -> Option<&NodeContainer> {
+        match (&self.node, component) {
+            (Node::Object(node_object), DataPathType::Name(name)) => node_object.nodes.get(name),
+            (Node::Enum(node_enum), DataPathType::Indice(pos)) => {
+                node_enum.nodes.get(*pos).map(|node| &**node)
+            }
+            (Node::Option(node_option), DataPathType::Indice(0)) => Some(&node_option.inner),
+            (Node::Array(node_array), DataPathType::Indice(pos)) => {
+                node_array.values.as_ref()?.get(*pos)
+            }
+            _ => None,
+        }
+    }
+
+    /// The part of `value`, a value of this node, that belongs to the child at `component`
+    fn child_value<'a>(&self, value: &'a Value, component: &DataPathType) -> Option<&'a Value> {
+        match (&self.node, component, value) {
+            (Node::Object(_), DataPathType::Name(name), Value::Dict(_, dict)) => dict.get(name),
+            (Node::Enum(node_enum), DataPathType::Indice(pos), value) => {
+                (node_enum.variant_of(value) == Some(*pos)).then_some(value)
+            }
+            (Node::Option(_), DataPathType::Indice(0), Value::Empty(..)) => None,
+            (Node::Option(_), DataPathType::Indice(0), value) => Some(value),
+            (Node::Array(_), DataPathType::Indice(pos), Value::Array(_, values)) => {
+                values.get(*pos)
+            }
+            _ => None,
+        }
+    }
+}
@@ -1,13 +1,14 @@
 use core::num;
 use std::{
     borrow::{BorrowMut, Cow},
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
 };
 
 use figment::value::{Empty, Num, Tag};
 use json::value::Index;
 use schemars::schema::{
-    InstanceType, RootSchema, Schema, SchemaObject, SingleOrVec, SubschemaValidation,
+    InstanceType, ObjectValidation, RootSchema, Schema, SchemaObject, SingleOrVec,
+    SubschemaValidation,
 };
 
 use super::*;
@@ -19,18 +20,45 @@ impl NodeContainer {
 }
 
 /// None means that the schema validate nothing
-#[instrument(skip_all)]
 pub(crate) fn schema_object_to_node(
     from: &str,
     def: &schemars::Map<String, Schema>,
     schema_object: &SchemaObject,
+) -> Option<NodeContainer> {
+    schema_object_to_node_impl(
+        from,
+        &mut Definitions {
+            schemas: def,
+            nodes: HashMap::new(),
+        },
+        schema_object,
+    )
+}
+
+/// The `$ref` definitions of a schema. Each definition is converted once, then cloned.
+/// Cloning is cheap because templates and enum variants are shared.
+struct Definitions<'a> {
+    schemas: &'a schemars::Map<String, Schema>,
+    nodes: HashMap<String, NodeContainer>,
+}
+
+#[instrument(skip_all)]
+fn schema_object_to_node_impl(
+    from: &str,
+    def: &mut Definitions,
+    schema_object: &SchemaObject,
 ) -> Option<NodeContainer> {
     // debug!("enter function from {from}.\n{:#?}", schema_object);
 
-    let mut res = NodeContainer::from_node(Node::Any);
+    let mut res = NodeContainer::from_node(Node::Any(NodeAny::new()));
 
     if let Some(single_or_vec) = &schema_object.instance_type {
-        fn instance_type_to_node(instance_type: &InstanceType, format: Option<&String>) -> Node {
+        fn instance_type_to_node(
+            instance_type: &InstanceType,
+            schema_object: &SchemaObject,
+        ) -> Node {
+            let format = schema_object.format.as_ref();
+
             match *instance_type {
                 InstanceType::Null => Node::Null,
                 InstanceType::Boolean => Node::Bool(NodeBool::new()),
@@ -41,6 +69,14 @@ pub(crate) fn schema_object_to_node(
                         .and_then(|s| NumberValue::kind_from_str(s))
                         .unwrap_or(NumberValueLight::F64),
                 )),
+                // this is how schemars describe a char
+                InstanceType::String
+                    if schema_object.string.as_ref().is_some_and(|string| {
+                        string.min_length == Some(1) && string.max_length == Some(1)
+                    }) =>
+                {
+                    Node::Char(NodeChar::new())
+                }
                 InstanceType::String => Node::String(NodeString::new()),
                 InstanceType::Integer => Node::Number(NodeNumber::new(
                     format
@@ -51,10 +87,9 @@ pub(crate) fn schema_object_to_node(
         }
 
         let node = match single_or_vec {
-            SingleOrVec::Single(instance_type) => NodeContainer::from_node(instance_type_to_node(
-                instance_type,
-                schema_object.format.as_ref(),
-            )),
+            SingleOrVec::Single(instance_type) => {
+                NodeContainer::from_node(instance_type_to_node(instance_type, schema_object))
+            }
             SingleOrVec::Vec(vec) => {
                 let nodes = vec
                     .iter()
@@ -63,11 +98,11 @@ pub(crate) fn schema_object_to_node(
 
                         NodeContainer::from_node(instance_type_to_node(
                             instance_type,
-                            schema_object.format.as_ref(),
+                            schema_object,
                         ))
                     })
                     .collect();
-                NodeContainer::from_node(Node::Enum(NodeEnum::new(nodes)))
+                enum_or_option(nodes)
             }
         };
 
@@ -78,7 +113,7 @@ pub(crate) fn schema_object_to_node(
         let mut nodes = IndexMap::new();
 
         for (name, type_definition) in &obj.properties {
-            let node = schema_object_to_node("object", def, &type_definition.to_object())?;
+            let node = schema_object_to_node_impl("object", def, &type_definition.to_object())?;
             nodes.insert(name.clone(), node);
         }
 
@@ -88,12 +123,14 @@ pub(crate) fn schema_object_to_node(
             obj.additional_properties
                 .as_ref()
                 .map(|additional_properties| {
-                    schema_object_to_node("object", def, &additional_properties.to_object())
+                    schema_object_to_node_impl("object", def, &additional_properties.to_object())
                 })?
         };
 
-        let node =
-            NodeContainer::from_node(Node::Object(NodeObject::new(nodes, additional_properties)));
+        let node = NodeContainer::from_node(Node::Object(NodeObject {
+            variant: variant_kind(obj),
+            ..NodeObject::new(nodes, additional_properties)
+        }));
 
         res = res.merge(&node)?;
     }
@@ -121,22 +158,25 @@ pub(crate) fn schema_object_to_node(
             Some(single_or_vec) => match single_or_vec {
                 // this means items of the array all share the type described by this schema
                 SingleOrVec::Single(schema) => {
-                    let node = schema_object_to_node("array single", def, &schema.to_object())?;
-                    NodeArrayTemplate::All(Box::new(node))
+                    let node =
+                        schema_object_to_node_impl("array single", def, &schema.to_object())?;
+                    NodeArrayTemplate::All(Arc::new(node))
                 }
                 // items are of type array.
                 SingleOrVec::Vec(vec) => {
                     let template: Option<Vec<_>> = vec
                         .iter()
                         .map(|schema| {
-                            schema_object_to_node("array multiple", def, &schema.to_object())
+                            schema_object_to_node_impl("array multiple", def, &schema.to_object())
                         })
                         .collect();
 
-                    NodeArrayTemplate::FirstN(template?)
+                    NodeArrayTemplate::FirstN(Arc::new(template?))
                 }
             },
-            None => NodeArrayTemplate::All(Box::new(NodeContainer::from_node(Node::Any))),
+            None => NodeArrayTemplate::All(Arc::new(NodeContainer::from_node(Node::Any(
+                NodeAny::new(),
+            )))),
         };
 
         let node = NodeContainer::from_node(Node::Array(NodeArray {
@@ -154,7 +194,7 @@ pub(crate) fn schema_object_to_node(
             let mut nodes = Vec::new();
 
             for schema in all_of {
-                let node = schema_object_to_node("all_of", def, &schema.to_object())?;
+                let node = schema_object_to_node_impl("all_of", def, &schema.to_object())?;
                 nodes.push(node);
             }
 
@@ -169,33 +209,40 @@ pub(crate) fn schema_object_to_node(
         if let Some(one_of) = &subschemas.one_of {
             let mut nodes = Vec::new();
             for schema in one_of {
-                let node = schema_object_to_node("one_of", def, &schema.to_object())?;
+                let node = schema_object_to_node_impl("one_of", def, &schema.to_object())?;
 
                 nodes.push(node);
             }
 
-            let node = NodeContainer::from_node(Node::Enum(NodeEnum::new(nodes)));
+            let node = enum_or_option(nodes);
             res = res.merge(&node)?;
         }
 
         if let Some(any_of) = &subschemas.any_of {
             let mut nodes = Vec::new();
             for schema in any_of {
-                let node = schema_object_to_node("one_of", def, &schema.to_object())?;
+                let node = schema_object_to_node_impl("one_of", def, &schema.to_object())?;
 
                 nodes.push(node);
             }
 
-            let node = NodeContainer::from_node(Node::Enum(NodeEnum::new(nodes)));
+            let node = enum_or_option(nodes);
             res = res.merge(&node)?;
         }
     }
 
     if let Some(definition) = &schema_object.reference {
         if let Some(definition) = definition.strip_prefix("#/definitions/") {
-            let schema = def.get(definition).unwrap();
-
-            let node = schema_object_to_node("definition", def, &schema.to_object())?;
+            let node = match def.nodes.get(definition) {
+                Some(node) => node.clone(),
+                None => {
+                    let schema = def.schemas.get(definition).unwrap();
+
+                    let node = schema_object_to_node_impl("definition", def, &schema.to_object())?;
+                    def.nodes.insert(definition.to_string(), node.clone());
+                    node
+                }
+            };
             res = res.merge(&node)?;
         }
     }
@@ -204,6 +251,54 @@ pub(crate) fn schema_object_to_node(
     Some(res)
 }
 
+/// schemars describe the externally tagged variant `B(..)` as an object
+/// with a single required property `B`, that doesn't allow other properties
+fn variant_kind(obj: &ObjectValidation) -> Option<VariantKind> {
+    if obj.properties.len() != 1
+        || !matches!(
+            obj.additional_properties.as_deref(),
+            Some(Schema::Bool(false))
+        )
+    {
+        return None;
+    }
+
+    let (name, schema) = obj.properties.iter().next()?;
+
+    if !obj.required.contains(name) {
+        return None;
+    }
+
+    let schema = schema.to_object();
+
+    let kind = if schema.reference.is_none() && schema.object.is_some() {
+        VariantKind::Struct
+    } else if schema
+        .array
+        .as_ref()
+        .is_some_and(|array| matches!(array.items, Some(SingleOrVec::Vec(_))))
+    {
+        VariantKind::Tuple
+    } else {
+        VariantKind::Newtype
+    };
+
+    Some(kind)
+}
+
+/// `Option<T>` is described as `T` or `null`
+fn enum_or_option(mut nodes: Vec<NodeContainer>) -> NodeContainer {
+    if nodes.len() == 2
+        && let Some(null_pos) = nodes.iter().position(|n| matches!(n.node, Node::Null))
+    {
+        nodes.remove(null_pos);
+
+        NodeContainer::from_node(Node::Option(NodeOption::new(nodes.remove(0))))
+    } else {
+        NodeContainer::from_node(Node::Enum(NodeEnum::new(nodes)))
+    }
+}
+
 pub trait ToSchemaObject {
     fn to_object(&self) -> Cow<'_, SchemaObject>;
 }
@@ -234,7 +329,14 @@ pub(crate) fn json_value_to_figment_value(json_value: &json::Value) -> Value {
             } else if let Some(n) = number.as_i64() {
                 Num::I64(n)
             } else if let Some(n) = number.as_f64() {
-                Num::F64(n)
+                // integers that don't fit in 64 bits are parsed as floats
+                if n.fract() == 0.0 && n >= u64::MAX as f64 && n < u128::MAX as f64 {
+                    Num::U128(n as u128)
+                } else if n.fract() == 0.0 && n >= i128::MIN as f64 && n < i64::MIN as f64 {
+                    Num::I128(n as i128)
+                } else {
+                    Num::F64(n)
+                }
             } else {
                 panic!("not a valid number")
             };
@@ -266,6 +368,7 @@ impl NodeContainer {
             (Node::Bool(node_bool), Node::Null) => Some(other.clone()),
             (Node::Bool(node_bool), Node::Bool(node_bool2)) => Some(other.clone()),
             (Node::String(node_string), Node::String(node_string2)) => Some(other.clone()),
+            (Node::Char(node_char), Node::Char(node_char2)) => Some(other.clone()),
             (Node::Number(node_number), Node::Number(node_number2)) => Some(other.clone()),
             (Node::Object(node_object), Node::Object(node_object2)) => Some(other.clone()),
             (Node::Enum(node_enum1), Node::Enum(node_enum2)) => todo!("product?"),
@@ -279,7 +382,7 @@ impl NodeContainer {
                     Some((pos, new)) => {
                         let mut node_self = self.clone();
                         let mut new_enum = node_enum.clone();
-                        new_enum.nodes[pos] = new;
+                        new_enum.nodes[pos] = Arc::new(new);
                         node_self.node = Node::Enum(new_enum);
 
                         Some(node_self)
@@ -293,8 +396,24 @@ impl NodeContainer {
             (Node::Array(node_array), Node::Array(node_array2)) => Some(other.clone()),
             (_, Node::Value(node_value2)) => Some(other.clone()),
             (Node::Value(node_value1), _) => Some(self.clone()),
-            (Node::Any, _) => Some(other.clone()),
-            (_, Node::Any) => Some(self.clone()),
+            (Node::Any(_), _) => Some(other.clone()),
+            (_, Node::Any(_)) => Some(self.clone()),
+            (Node::Option(node_option), Node::Null) => Some(self.clone()),
+            (Node::Option(node_option), _) => {
+                let inner = node_option.inner.merge(other)?;
+
+                let mut node_self = self.clone();
+                node_self.node.unwrap_option_mut().inner = Box::new(inner);
+                Some(node_self)
+            }
+            (Node::Null, Node::Option(node_option)) => Some(other.clone()),
+            (_, Node::Option(node_option)) => {
+                let inner = node_option.inner.merge(self)?;
+
+                let mut node_other = other.clone();
+                node_other.node.unwrap_option_mut().inner = Box::new(inner);
+                Some(node_other)
+            }
             (_, Node::Enum(node_enum)) => {
                 match node_enum
                     .nodes
@@ -305,7 +424,7 @@ impl NodeContainer {
                     Some((pos, new)) => {
                         let mut node_other = other.clone();
                         let mut new_enum = node_enum.clone();
-                        new_enum.nodes[pos] = new;
+                        new_enum.nodes[pos] = Arc::new(new);
                         node_other.node = Node::Enum(new_enum);
 
                         Some(node_other)
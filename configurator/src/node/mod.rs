@@ -1,20 +1,27 @@
-use std::{borrow::Cow, collections::BTreeMap, fmt::Display};
+use std::{borrow::Cow, collections::BTreeMap, fmt::Display, sync::Arc};
 
+use anyhow::bail;
 use derive_more::derive::Unwrap;
-use figment::value::{Num, Tag, Value};
+use figment::value::{Dict, Num, Tag, Value};
 use from_json_schema::json_value_to_figment_value;
 use indexmap::IndexMap;
 use light_enum::LightEnum;
 use schemars::schema::SchemaObject;
 
-use crate::utils::{figment_value_to_f64, figment_value_to_i128};
+use crate::{
+    providers::ron_value::RonValue,
+    utils::{figment_value_to_f64, figment_value_to_i128},
+};
 
 mod apply_figment;
 pub mod data_path;
+pub mod effective_default;
 pub mod from_json_schema;
 mod number;
 pub use number::{NumberValue, NumberValueLight};
+pub mod search;
 mod ser;
+pub(crate) use ser::intern;
 #[cfg(test)]
 mod tests;
 mod to_figment_value;
@@ -43,6 +50,25 @@ impl NodeContainer {
             removable: false,
         }
     }
+
+    /// Load the localized keys of the objects as settings, see [`NodeObject::localized_keys`].
+    /// Only the objects and their options are visited: keyfiles have groups of keys.
+    pub fn enable_localized_keys(&mut self) {
+        match &mut self.node {
+            Node::Object(node_object) => {
+                node_object.localized_keys = true;
+
+                for node in node_object.nodes.values_mut() {
+                    node.enable_localized_keys();
+                }
+                if let Some(template) = &mut node_object.template {
+                    Arc::make_mut(template).enable_localized_keys();
+                }
+            }
+            Node::Option(node_option) => node_option.inner.enable_localized_keys(),
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Clone, Unwrap)]
@@ -51,15 +77,17 @@ pub enum Node {
     Null,
     Bool(NodeBool),
     String(NodeString),
+    Char(NodeChar),
     Number(NodeNumber),
-    // Option(NodeOption),
+    Option(NodeOption),
     Object(NodeObject),
     Enum(NodeEnum),
     Array(NodeArray),
     /// represent a final value
     /// currently only string is supported
     Value(NodeValue),
-    Any,
+    /// Any value is valid, e.g `{}` or `true` schemas
+    Any(NodeAny),
 }
 
 #[derive(Debug, Clone)]
@@ -83,6 +111,12 @@ pub struct NodeString {
     pub value: Option<String>,
 }
 
+/// A string of exactly one character
+#[derive(Debug, Clone)]
+pub struct NodeChar {
+    pub value: Option<char>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NodeNumber {
     pub kind: NumberValueLight,
@@ -95,29 +129,71 @@ pub struct NodeValue {
     pub value: json::Value,
 }
 
+/// `Option<T>`. The inner node is kept when the option is disabled
+#[derive(Debug, Clone)]
+pub struct NodeOption {
+    pub enabled: Option<bool>,
+    pub inner: Box<NodeContainer>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeAny {
+    pub value: Option<Value>,
+    /// Text edited by the user, in JSON or RON
+    pub value_string: String,
+    /// Why `value_string` can't be parsed. `value` is then the last valid value
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct NodeEnum {
     pub value: Option<usize>,
-    pub nodes: Vec<NodeContainer>,
+    /// Variants are shared until they are modified, see [`NodeEnum::variant_mut`]
+    pub nodes: Vec<Arc<NodeContainer>>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct NodeObject {
     pub nodes: IndexMap<String, NodeContainer>,
-    pub template: Option<Box<NodeContainer>>,
+    /// Shared between all the clones of this node. An `Arc` rather than an arena:
+    /// the pages clone and edit their trees freely, and [`Arc::make_mut`] copies
+    /// a template only when it is modified, without an arena to pass around.
+    pub template: Option<Arc<NodeContainer>>,
+    /// Set when this object is the `{ "Variant": inner }` representation
+    /// of an externally tagged enum variant
+    pub variant: Option<VariantKind>,
+    /// Values whose key is not in the schema. They are written back as is.
+    pub unknown: Dict,
+    /// The unknown values as they were read in a RON file, see [`NodeObject::unknown_ron`]
+    pub unknown_ron: BTreeMap<String, RonValue>,
+    /// The keys can have a locale, like in keyfiles: `Name[fr]` is a setting typed like `Name`
+    pub localized_keys: bool,
+}
+
+/// Shape of the data of an enum variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantKind {
+    /// `B(i32)`
+    Newtype,
+    /// `B(i32, String)`
+    Tuple,
+    /// `D { a: i32 }`
+    Struct,
 }
 
 #[derive(Debug, Clone)]
 pub enum NodeArrayTemplate {
-    All(Box<NodeContainer>),
-    FirstN(Vec<NodeContainer>),
+    All(Arc<NodeContainer>),
+    FirstN(Arc<Vec<NodeContainer>>),
 }
 
 #[derive(Debug, Clone)]
 pub struct NodeArray {
     pub values: Option<Vec<NodeContainer>>,
     pub template: NodeArrayTemplate,
+    /// Minimum number of values
     pub min: Option<u32>,
+    /// Maximum number of values
     pub max: Option<u32>,
 }
 
@@ -133,30 +209,152 @@ impl NodeString {
     }
 }
 
+impl NodeChar {
+    pub fn new() -> Self {
+        Self { value: None }
+    }
+}
+
 impl NodeValue {
     pub fn new(value: json::Value) -> Self {
         Self { value }
     }
 }
 
+impl NodeOption {
+    pub fn new(inner: NodeContainer) -> Self {
+        Self {
+            enabled: None,
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl NodeAny {
+    pub fn new() -> Self {
+        Self {
+            value: None,
+            value_string: String::new(),
+            error: None,
+        }
+    }
+
+    pub fn set_value(&mut self, value: Value) {
+        self.value_string = json::to_string(&value).unwrap_or_default();
+        self.value = Some(value);
+        self.error = None;
+    }
+
+    /// Parse the text typed by the user. Return `false` when it's not a valid value
+    pub fn set_value_string(&mut self, value_string: String) -> bool {
+        self.value_string = value_string;
+
+        match crate::utils::figment_value_from_str(&self.value_string) {
+            Ok(value) => {
+                self.value = Some(value);
+                self.error = None;
+                true
+            }
+            Err(err) => {
+                self.error = Some(err.to_string());
+                false
+            }
+        }
+    }
+}
+
 impl NodeEnum {
     pub fn new(nodes: Vec<NodeContainer>) -> Self {
-        Self { value: None, nodes }
+        Self {
+            value: None,
+            nodes: nodes.into_iter().map(Arc::new).collect(),
+        }
     }
+
+    /// Copy the variant if it is shared with other nodes
+    pub fn variant_mut(&mut self, pos: usize) -> &mut NodeContainer {
+        Arc::make_mut(&mut self.nodes[pos])
+    }
+}
+
+/// `Name[fr]` -> `Name`, for the localized keys of keyfiles
+pub fn without_locale(key: &str) -> Option<&str> {
+    key.strip_suffix(']')?
+        .rsplit_once('[')
+        .map(|(key, _locale)| key)
 }
 
 impl NodeObject {
     pub fn new(nodes: IndexMap<String, NodeContainer>, node_type: Option<NodeContainer>) -> Self {
         Self {
             nodes,
-            template: node_type.map(Box::new),
+            template: node_type.map(Arc::new),
+            variant: None,
+            unknown: Dict::new(),
+            unknown_ron: BTreeMap::new(),
+            localized_keys: false,
         }
     }
 
+    /// The RON value of an unknown key, unless it changed since it was read.
+    /// Figment values lose the enum variants and the `Some`, so RON is written from it.
+    pub fn unknown_ron(&self, key: &str) -> Option<&RonValue> {
+        let value = self.unknown_ron.get(key)?;
+
+        (self.unknown.get(key) == Some(&value.to_value())).then_some(value)
+    }
+
+    /// Add the localized keys of `values`, like `Name[fr]`, as nodes typed like `Name`.
+    /// Return the values whose key is not in the schema, or that don't fit their node.
+    pub(super) fn apply_localized(&mut self, values: Dict, modified: bool) -> Dict {
+        if !self.localized_keys {
+            return values;
+        }
+
+        let mut unknown = Dict::new();
+
+        for (key, value) in values {
+            let Some(base) = without_locale(&key) else {
+                unknown.insert(key, value);
+                continue;
+            };
+            let Some(pos) = self.nodes.get_index_of(base) else {
+                unknown.insert(key, value);
+                continue;
+            };
+
+            let mut node = self.nodes[pos].clone();
+            node.remove_value_rec();
+            node.removable = true;
+
+            if node.apply_value(value.clone(), modified).is_err() {
+                unknown.insert(key, value);
+                continue;
+            }
+
+            // after the key and its other locales
+            let end = pos
+                + 1
+                + self.nodes[pos + 1..]
+                    .keys()
+                    .take_while(|other| without_locale(other) == Some(base))
+                    .count();
+
+            self.nodes.shift_insert(end, key, node);
+        }
+
+        unknown
+    }
+
+    /// Fixed set of fields, as opposed to a map or an enum variant
+    pub fn is_struct(&self) -> bool {
+        self.template.is_none() && self.variant.is_none()
+    }
+
     pub fn template(&self) -> Option<NodeContainer> {
         match &self.template {
             Some(template) => {
-                let mut template = *template.clone();
+                let mut template = (**template).clone();
                 template.removable = true;
                 Some(template)
             }
@@ -169,7 +367,9 @@ impl NodeArray {
     pub fn new_any() -> Self {
         Self {
             values: None,
-            template: NodeArrayTemplate::All(Box::new(NodeContainer::from_node(Node::Any))),
+            template: NodeArrayTemplate::All(Arc::new(NodeContainer::from_node(Node::Any(
+                NodeAny::new(),
+            )))),
             min: None,
             max: None,
         }
@@ -179,7 +379,7 @@ impl NodeArray {
         match &self.template {
             NodeArrayTemplate::All(new_node) => {
                 let mut new_node = (**new_node).clone();
-                new_node.removable = true;
+                new_node.removable = !self.is_fixed_len();
                 new_node
             }
             NodeArrayTemplate::FirstN(vec) => {
@@ -195,6 +395,49 @@ impl NodeArray {
             }
         }
     }
+
+    /// Tuples and fixed-size arrays, e.g `(u8, u8, u8)` or `[f32; 4]`
+    pub fn is_fixed_len(&self) -> bool {
+        self.min.is_some() && self.min == self.max
+    }
+
+    fn max_len(&self) -> Option<usize> {
+        let max = self.max.map(|max| max as usize);
+
+        match &self.template {
+            NodeArrayTemplate::All(_) => max,
+            NodeArrayTemplate::FirstN(vec) => Some(max.map_or(vec.len(), |max| max.min(vec.len()))),
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.values.as_ref().map_or(0, |values| values.len())
+    }
+
+    pub fn can_add(&self) -> bool {
+        self.max_len().is_none_or(|max| self.len() < max)
+    }
+
+    pub fn can_remove(&self) -> bool {
+        self.min.is_none_or(|min| self.len() > min as usize)
+    }
+
+    /// Fail if an array of `len` values is not allowed by the schema
+    pub fn check_len(&self, len: usize) -> anyhow::Result<()> {
+        if let Some(min) = self.min
+            && len < min as usize
+        {
+            bail!("expected at least {min} values, found {len}");
+        }
+
+        if let Some(max) = self.max_len()
+            && len > max
+        {
+            bail!("expected at most {max} values, found {len}");
+        }
+
+        Ok(())
+    }
 }
 
 impl NodeContainer {
@@ -204,7 +447,11 @@ impl NodeContainer {
             Node::Null => true,
             Node::Bool(node_bool) => node_bool.value.is_some(),
             Node::String(node_string) => node_string.value.is_some(),
+            Node::Char(node_char) => node_char.value.is_some(),
             Node::Number(node_number) => node_number.value.is_some(),
+            Node::Option(node_option) => node_option
+                .enabled
+                .is_some_and(|enabled| !enabled || node_option.inner.is_valid()),
             Node::Object(node_object) => node_object.nodes.values().all(|n| n.is_valid()),
             Node::Enum(node_enum) => node_enum
                 .value
@@ -214,7 +461,24 @@ impl NodeContainer {
                 .as_ref()
                 .is_some_and(|values| values.iter().all(|n| n.is_valid())),
             Node::Value(node_value) => true,
-            Node::Any => true,
+            Node::Any(node_any) => node_any.value.is_some(),
+        }
+    }
+
+    /// Return true if this node was given a value, even partially
+    pub fn has_value(&self) -> bool {
+        match &self.node {
+            Node::Null => true,
+            Node::Bool(node_bool) => node_bool.value.is_some(),
+            Node::String(node_string) => node_string.value.is_some(),
+            Node::Char(node_char) => node_char.value.is_some(),
+            Node::Number(node_number) => node_number.value.is_some(),
+            Node::Option(node_option) => node_option.enabled.is_some(),
+            Node::Object(node_object) => node_object.nodes.values().any(|n| n.has_value()),
+            Node::Enum(node_enum) => node_enum.value.is_some(),
+            Node::Array(node_array) => node_array.values.is_some(),
+            Node::Value(node_value) => true,
+            Node::Any(node_any) => node_any.value.is_some(),
         }
     }
 
@@ -235,12 +499,14 @@ impl NodeContainer {
             Node::Null => Some(Cow::Borrowed("Null")),
             Node::Bool(node_bool) => None,
             Node::String(node_string) => None,
+            Node::Char(node_char) => None,
             Node::Number(node_number) => None,
+            Node::Option(node_option) => None,
             Node::Object(node_object) => None,
             Node::Enum(node_enum) => None,
             Node::Array(node_array) => None,
             Node::Value(node_value) => node_value.value.as_str().map(Cow::Borrowed),
-            Node::Any => Some(Cow::Borrowed("Any")),
+            Node::Any(node_any) => Some(Cow::Borrowed("Any")),
         }
     }
 }
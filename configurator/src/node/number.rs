@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{fmt::Display, str::FromStr, sync::LazyLock};
 
 use anyhow::bail;
 use figment::value::Num;
@@ -42,7 +42,7 @@ impl Display for NumberValue {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             NumberValue::I128(n) => write!(f, "{}", n),
-            NumberValue::F64(n) => write!(f, "{:.3}", n),
+            NumberValue::F64(n) => write!(f, "{}", n),
             NumberValue::U8(n) => write!(f, "{}", n),
             NumberValue::U16(n) => write!(f, "{}", n),
             NumberValue::U32(n) => write!(f, "{}", n),
@@ -54,7 +54,7 @@ impl Display for NumberValue {
             NumberValue::I32(n) => write!(f, "{}", n),
             NumberValue::I64(n) => write!(f, "{}", n),
             NumberValue::ISize(n) => write!(f, "{}", n),
-            NumberValue::F32(n) => write!(f, "{:.3}", n),
+            NumberValue::F32(n) => write!(f, "{}", n),
         }
     }
 }
@@ -85,6 +85,24 @@ impl NumberValue {
         Some(v)
     }
 
+    /// Floats are displayed with the shortest representation that parses back
+    /// to the same value. This returns the exact decimal expansion instead.
+    pub fn to_string_exact(&self) -> String {
+        let float = match self {
+            NumberValue::F32(n) => f64::from(*n),
+            NumberValue::F64(n) => *n,
+            _ => return self.to_string(),
+        };
+
+        if !float.is_finite() {
+            return float.to_string();
+        }
+
+        // enough for the smallest subnormal
+        let str = format!("{float:.1100}");
+        str.trim_end_matches('0').trim_end_matches('.').to_string()
+    }
+
     pub fn into_num(self) -> Num {
         match self {
             NumberValue::U8(v) => Num::U8(v),
@@ -135,25 +153,201 @@ impl NodeNumber {
         self.try_parse_from_str(&s)
     }
 
+    /// Integers can be written in decimal, hexadecimal (`0x`), octal (`0o`), binary (`0b`)
+    /// or scientific notation, as long as the value is integral.
+    /// Floats accept the decimal separator of the user's locale.
+    /// Underscores are ignored.
     pub fn try_parse_from_str(&self, str: &str) -> anyhow::Result<NumberValue> {
+        let separator = *LOCALE_DECIMAL_SEPARATOR;
+
         let v = match self.kind {
-            NumberValueLight::U8 if let Ok(v) = str.parse::<u8>() => NumberValue::U8(v),
-            NumberValueLight::U16 if let Ok(v) = str.parse::<u16>() => NumberValue::U16(v),
-            NumberValueLight::U32 if let Ok(v) = str.parse::<u32>() => NumberValue::U32(v),
-            NumberValueLight::U64 if let Ok(v) = str.parse::<u64>() => NumberValue::U64(v),
-            NumberValueLight::U128 if let Ok(v) = str.parse::<u128>() => NumberValue::U128(v),
-            NumberValueLight::USize if let Ok(v) = str.parse::<usize>() => NumberValue::USize(v),
-            NumberValueLight::I8 if let Ok(v) = str.parse::<i8>() => NumberValue::I8(v),
-            NumberValueLight::I16 if let Ok(v) = str.parse::<i16>() => NumberValue::I16(v),
-            NumberValueLight::I32 if let Ok(v) = str.parse::<i32>() => NumberValue::I32(v),
-            NumberValueLight::I64 if let Ok(v) = str.parse::<i64>() => NumberValue::I64(v),
-            NumberValueLight::I128 if let Ok(v) = str.parse::<i128>() => NumberValue::I128(v),
-            NumberValueLight::ISize if let Ok(v) = str.parse::<isize>() => NumberValue::ISize(v),
-            NumberValueLight::F32 if let Ok(v) = str.parse::<f32>() => NumberValue::F32(v),
-            NumberValueLight::F64 if let Ok(v) = str.parse::<f64>() => NumberValue::F64(v),
+            NumberValueLight::U8 if let Some(v) = parse_int(str) => NumberValue::U8(v),
+            NumberValueLight::U16 if let Some(v) = parse_int(str) => NumberValue::U16(v),
+            NumberValueLight::U32 if let Some(v) = parse_int(str) => NumberValue::U32(v),
+            NumberValueLight::U64 if let Some(v) = parse_int(str) => NumberValue::U64(v),
+            NumberValueLight::U128 if let Some(v) = parse_int(str) => NumberValue::U128(v),
+            NumberValueLight::USize if let Some(v) = parse_int(str) => NumberValue::USize(v),
+            NumberValueLight::I8 if let Some(v) = parse_int(str) => NumberValue::I8(v),
+            NumberValueLight::I16 if let Some(v) = parse_int(str) => NumberValue::I16(v),
+            NumberValueLight::I32 if let Some(v) = parse_int(str) => NumberValue::I32(v),
+            NumberValueLight::I64 if let Some(v) = parse_int(str) => NumberValue::I64(v),
+            NumberValueLight::I128 if let Some(v) = parse_int(str) => NumberValue::I128(v),
+            NumberValueLight::ISize if let Some(v) = parse_int(str) => NumberValue::ISize(v),
+            NumberValueLight::F32 if let Some(v) = parse_float(str, separator) => {
+                NumberValue::F32(v)
+            }
+            NumberValueLight::F64 if let Some(v) = parse_float(str, separator) => {
+                NumberValue::F64(v)
+            }
             _ => bail!("can't parse {} to {:?}", str, self.kind),
         };
 
         Ok(v)
     }
+
+    /// Parse a number written in a file, with Rust's syntax whatever the locale
+    pub fn try_parse_plain(&self, str: &str) -> anyhow::Result<NumberValue> {
+        let str = str.trim();
+
+        let v = match self.kind {
+            NumberValueLight::U8 => NumberValue::U8(str.parse()?),
+            NumberValueLight::U16 => NumberValue::U16(str.parse()?),
+            NumberValueLight::U32 => NumberValue::U32(str.parse()?),
+            NumberValueLight::U64 => NumberValue::U64(str.parse()?),
+            NumberValueLight::U128 => NumberValue::U128(str.parse()?),
+            NumberValueLight::USize => NumberValue::USize(str.parse()?),
+            NumberValueLight::I8 => NumberValue::I8(str.parse()?),
+            NumberValueLight::I16 => NumberValue::I16(str.parse()?),
+            NumberValueLight::I32 => NumberValue::I32(str.parse()?),
+            NumberValueLight::I64 => NumberValue::I64(str.parse()?),
+            NumberValueLight::I128 => NumberValue::I128(str.parse()?),
+            NumberValueLight::ISize => NumberValue::ISize(str.parse()?),
+            NumberValueLight::F32 => NumberValue::F32(str.parse()?),
+            NumberValueLight::F64 => NumberValue::F64(str.parse()?),
+        };
+
+        Ok(v)
+    }
+}
+
+fn parse_int<T>(str: &str) -> Option<T>
+where
+    T: TryFrom<i128> + TryFrom<u128>,
+{
+    let str = str.trim().replace('_', "");
+
+    let (negative, digits) = match str.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, str.strip_prefix('+').unwrap_or(&str)),
+    };
+
+    let abs = match digits.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => u128::from_str_radix(&digits[2..], 16).ok()?,
+        Some("0o") => u128::from_str_radix(&digits[2..], 8).ok()?,
+        Some("0b") => u128::from_str_radix(&digits[2..], 2).ok()?,
+        _ => parse_scientific_int(digits)?,
+    };
+
+    if negative {
+        T::try_from(0i128.checked_sub_unsigned(abs)?).ok()
+    } else {
+        T::try_from(abs).ok()
+    }
+}
+
+/// Parse `1`, `1.5e3` or `100e-2`, but not `1.5`
+fn parse_scientific_int(str: &str) -> Option<u128> {
+    let (mantissa, exp) = match str.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => (mantissa, exp.parse::<i32>().ok()?),
+        None => (str, 0),
+    };
+
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    if int.is_empty() && frac.is_empty()
+        || !int.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut digits = format!("{int}{frac}");
+    let mut exp = exp.checked_sub(frac.len().try_into().ok()?)?;
+
+    // the digits after the decimal point must be zeros
+    while exp < 0 {
+        match digits.pop() {
+            Some('0') => exp += 1,
+            Some(_) => return None,
+            None => break,
+        }
+    }
+
+    let value = if digits.is_empty() {
+        0
+    } else {
+        digits.parse::<u128>().ok()?
+    };
+
+    if value == 0 {
+        return Some(0);
+    }
+
+    value.checked_mul(10u128.checked_pow(exp.try_into().ok()?)?)
+}
+
+fn parse_float<T: FromStr>(str: &str, separator: char) -> Option<T> {
+    let mut str = str.trim().replace('_', "");
+
+    if separator != '.' && !str.contains('.') {
+        str = str.replacen(separator, ".", 1);
+    }
+
+    str.parse().ok()
+}
+
+/// Decimal separator of the locale used for numbers (`LC_ALL`, `LC_NUMERIC`, then `LANG`)
+static LOCALE_DECIMAL_SEPARATOR: LazyLock<char> = LazyLock::new(|| {
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .into_iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty())
+        .unwrap_or_default();
+
+    decimal_separator(&locale)
+});
+
+fn decimal_separator(locale: &str) -> char {
+    // de_CH.UTF-8 -> ("de", "CH")
+    let locale = locale.split(['.', '@']).next().unwrap_or_default();
+    let (lang, territory) = locale.split_once('_').unwrap_or((locale, ""));
+
+    match (lang, territory) {
+        (_, "CH") | ("es", "MX" | "US") => '.',
+        (
+            "af" | "az" | "be" | "bg" | "bs" | "ca" | "cs" | "da" | "de" | "el" | "es" | "et"
+            | "eu" | "fi" | "fr" | "gl" | "hr" | "hu" | "hy" | "id" | "is" | "it" | "ka" | "kk"
+            | "lt" | "lv" | "mk" | "nb" | "nl" | "nn" | "no" | "pl" | "pt" | "ro" | "ru" | "sk"
+            | "sl" | "sq" | "sr" | "sv" | "tr" | "uk" | "uz" | "vi",
+            _,
+        ) => ',',
+        _ => '.',
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decimal_separator, parse_float, parse_int};
+
+    #[test]
+    fn test_parse_int() {
+        assert_eq!(parse_int::<u8>("0xff"), Some(255));
+        assert_eq!(parse_int::<u8>("0x100"), None);
+        assert_eq!(parse_int::<i32>("-0o17"), Some(-15));
+        assert_eq!(parse_int::<u32>("0b1010_1010"), Some(0b1010_1010));
+        assert_eq!(parse_int::<u64>("1_000_000"), Some(1_000_000));
+        assert_eq!(parse_int::<u64>("1e6"), Some(1_000_000));
+        assert_eq!(parse_int::<u64>("1.5E3"), Some(1500));
+        assert_eq!(parse_int::<u64>("1500e-3"), None);
+        assert_eq!(parse_int::<u64>("1000e-3"), Some(1));
+        assert_eq!(parse_int::<u64>("-1"), None);
+        assert_eq!(parse_int::<i8>("-128"), Some(i8::MIN));
+        assert_eq!(parse_int::<i128>(&i128::MIN.to_string()), Some(i128::MIN));
+        assert_eq!(parse_int::<u128>(&u128::MAX.to_string()), Some(u128::MAX));
+        assert_eq!(parse_int::<u64>(""), None);
+        assert_eq!(parse_int::<u64>("e3"), None);
+    }
+
+    #[test]
+    fn test_parse_float() {
+        assert_eq!(parse_float::<f64>("0.12345", '.'), Some(0.12345));
+        assert_eq!(parse_float::<f64>("1.5e-3", '.'), Some(0.0015));
+        assert_eq!(parse_float::<f64>("0,5", ','), Some(0.5));
+        assert_eq!(parse_float::<f64>("0,5", '.'), None);
+        assert_eq!(parse_float::<f32>("1_000.25", '.'), Some(1000.25));
+
+        assert_eq!(decimal_separator("fr_FR.UTF-8"), ',');
+        assert_eq!(decimal_separator("de_CH.UTF-8"), '.');
+        assert_eq!(decimal_separator("en_US.UTF-8"), '.');
+        assert_eq!(decimal_separator("C"), '.');
+    }
 }
@@ -0,0 +1,108 @@
+use std::borrow::Cow;
+
+use super::{data_path::DataPathType, Node, NodeContainer};
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub data_path: Vec<DataPathType>,
+    /// The text that matched the query (key, title, description or value)
+    pub matched: String,
+}
+
+impl NodeContainer {
+    /// Return every node under this one whose key, title, description
+    /// or current value contains `query`. The match is case insensitive.
+    pub fn search(&self, query: &str) -> Vec<SearchResult> {
+        let mut results = Vec::new();
+
+        let query = query.trim().to_lowercase();
+
+        if !query.is_empty() {
+            self.search_rec(&query, &mut Vec::new(), &mut results);
+        }
+
+        results
+    }
+
+    fn search_rec(
+        &self,
+        query: &str,
+        data_path: &mut Vec<DataPathType>,
+        results: &mut Vec<SearchResult>,
+    ) {
+        // the root node is the page itself
+        if let Some(last) = data_path.last() {
+            let key = match last {
+                DataPathType::Name(name) => Some(Cow::Borrowed(name.as_str())),
+                DataPathType::Indice(_) => None,
+            };
+
+            let matched = [key, self.title.as_deref().map(Cow::Borrowed)]
+                .into_iter()
+                .chain([self.desc.as_deref().map(Cow::Borrowed), self.value_string()])
+                .flatten()
+                .find(|text| text.to_lowercase().contains(query));
+
+            if let Some(matched) = matched {
+                results.push(SearchResult {
+                    data_path: data_path.clone(),
+                    matched: matched.into_owned(),
+                });
+            }
+        }
+
+        let mut search_child = |component: DataPathType, node: &NodeContainer| {
+            data_path.push(component);
+            node.search_rec(query, data_path, results);
+            data_path.pop();
+        };
+
+        match &self.node {
+            Node::Object(node_object) => {
+                for (key, node) in &node_object.nodes {
+                    search_child(DataPathType::Name(key.clone()), node);
+                }
+            }
+            Node::Enum(node_enum) => {
+                if let Some(pos) = node_enum.value {
+                    search_child(DataPathType::Indice(pos), &node_enum.nodes[pos]);
+                }
+            }
+            Node::Option(node_option) => {
+                if node_option.enabled == Some(true) {
+                    search_child(DataPathType::Indice(0), &node_option.inner);
+                }
+            }
+            Node::Array(node_array) => {
+                if let Some(values) = &node_array.values {
+                    for (pos, node) in values.iter().enumerate() {
+                        search_child(DataPathType::Indice(pos), node);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Textual representation of the current value of a leaf node
+    fn value_string(&self) -> Option<Cow<'_, str>> {
+        match &self.node {
+            Node::Bool(node_bool) => node_bool.value.map(|v| Cow::Owned(v.to_string())),
+            Node::String(node_string) => node_string.value.as_deref().map(Cow::Borrowed),
+            Node::Char(node_char) => node_char.value.map(|v| Cow::Owned(v.to_string())),
+            Node::Number(node_number) => node_number
+                .value
+                .as_ref()
+                .map(|_| Cow::Borrowed(node_number.value_string.as_str())),
+            Node::Value(node_value) => Some(match node_value.value.as_str() {
+                Some(str) => Cow::Borrowed(str),
+                None => Cow::Owned(node_value.value.to_string()),
+            }),
+            Node::Any(node_any) => node_any
+                .value
+                .as_ref()
+                .map(|_| Cow::Borrowed(node_any.value_string.as_str())),
+            _ => None,
+        }
+    }
+}
@@ -3,38 +3,297 @@ use std::{
     sync::{LazyLock, Mutex},
 };
 
+use figment::value::Tag;
 use serde::{
-    ser::{SerializeMap, SerializeStruct, SerializeTuple},
+    ser::{
+        Error, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleVariant,
+    },
     Serialize,
 };
 
-use super::NodeContainer;
+use super::{Node, NodeArrayTemplate, NodeContainer, NumberValue, VariantKind};
+
+/// Serialize a tree with the options of a format
+#[derive(Clone, Copy)]
+pub struct TreeSerializer<'a> {
+    node: &'a NodeContainer,
+    /// Write the variants with data as `{ "Variant": data }`, like figment values.
+    /// YAML would write them as tags (`!Variant`), that can't be read back.
+    variants_as_maps: bool,
+}
+
+impl NodeContainer {
+    pub fn serialize_variants_as_maps(&self) -> TreeSerializer<'_> {
+        TreeSerializer {
+            node: self,
+            variants_as_maps: true,
+        }
+    }
+}
+
+impl TreeSerializer<'_> {
+    fn with<'b>(&self, node: &'b NodeContainer) -> TreeSerializer<'b> {
+        TreeSerializer { node, ..*self }
+    }
+}
 
 impl Serialize for NodeContainer {
     fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
+        TreeSerializer {
+            node: self,
+            variants_as_maps: false,
+        }
+        .serialize(ser)
+    }
+}
+
+impl Serialize for TreeSerializer<'_> {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match &self.node.node {
+            Node::Null => ser.serialize_unit(),
+            Node::Bool(node_bool) => ser.serialize_bool(value_or_err::<S, _>(node_bool.value)?),
+            Node::String(node_string) => {
+                ser.serialize_str(value_or_err::<S, _>(node_string.value.as_ref())?)
+            }
+            Node::Char(node_char) => ser.serialize_char(value_or_err::<S, _>(node_char.value)?),
+            Node::Number(node_number) => match value_or_err::<S, _>(node_number.value.as_ref())? {
+                NumberValue::U8(v) => ser.serialize_u8(*v),
+                NumberValue::U16(v) => ser.serialize_u16(*v),
+                NumberValue::U32(v) => ser.serialize_u32(*v),
+                NumberValue::U64(v) => ser.serialize_u64(*v),
+                NumberValue::U128(v) => ser.serialize_u128(*v),
+                NumberValue::USize(v) => ser.serialize_u64(*v as u64),
+                NumberValue::I8(v) => ser.serialize_i8(*v),
+                NumberValue::I16(v) => ser.serialize_i16(*v),
+                NumberValue::I32(v) => ser.serialize_i32(*v),
+                NumberValue::I64(v) => ser.serialize_i64(*v),
+                NumberValue::I128(v) => ser.serialize_i128(*v),
+                NumberValue::ISize(v) => ser.serialize_i64(*v as i64),
+                NumberValue::F32(v) => ser.serialize_f32(*v),
+                NumberValue::F64(v) => ser.serialize_f64(*v),
+            },
+            Node::Option(node_option) => match value_or_err::<S, _>(node_option.enabled)? {
+                true => ser.serialize_some(&self.with(&node_option.inner)),
+                false => ser.serialize_none(),
+            },
+            Node::Object(node_object) => {
+                // the order of the IndexMap is kept
+                let nodes = node_object
+                    .nodes
+                    .iter()
+                    .filter(|(_, node)| node.will_be_written());
+
+                if node_object.template.is_some() {
+                    let mut map = ser.serialize_map(None)?;
+
+                    for (key, node) in nodes {
+                        map.serialize_entry(key, &self.with(node))?;
+                    }
+
+                    map.end()
+                } else {
+                    let len = nodes.clone().count() + node_object.unknown.len();
+                    let mut map = ser.serialize_struct("", len)?;
+
+                    for (key, node) in nodes {
+                        map.serialize_field(intern(key), &self.with(node))?;
+                    }
+
+                    for (key, value) in &node_object.unknown {
+                        match node_object.unknown_ron(key) {
+                            Some(value) => map.serialize_field(intern(key), value)?,
+                            None => map.serialize_field(intern(key), value)?,
+                        }
+                    }
+
+                    map.end()
+                }
+            }
+            Node::Enum(node_enum) => {
+                let pos = value_or_err::<S, _>(node_enum.value)?;
+                let variant = &node_enum.nodes[pos];
+
+                match &variant.node {
+                    Node::Value(node_value) if let Some(name) = node_value.value.as_str() => {
+                        ser.serialize_unit_variant("", pos as u32, intern(name))
+                    }
+                    Node::Object(node_object) if let Some(kind) = node_object.variant => {
+                        let (name, inner) = value_or_err::<S, _>(node_object.nodes.first())?;
+                        self.serialize_variant(ser, pos as u32, intern(name), kind, inner)
+                    }
+                    _ => self.with(variant).serialize(ser),
+                }
+            }
+            Node::Array(node_array) => {
+                let values = value_or_err::<S, _>(node_array.values.as_ref())?;
+
+                // tuples and fixed size arrays
+                if matches!(node_array.template, NodeArrayTemplate::FirstN(_))
+                    || node_array.min.is_some() && node_array.min == node_array.max
+                {
+                    let mut tuple = ser.serialize_tuple(values.len())?;
+
+                    for node in values {
+                        tuple.serialize_element(&self.with(node))?;
+                    }
+
+                    tuple.end()
+                } else {
+                    let mut seq = ser.serialize_seq(Some(values.len()))?;
+
+                    for node in values {
+                        seq.serialize_element(&self.with(node))?;
+                    }
+
+                    seq.end()
+                }
+            }
+            Node::Value(node_value) => node_value.value.serialize(ser),
+            Node::Any(node_any) => value_or_err::<S, _>(node_any.value.as_ref())?.serialize(ser),
+        }
+    }
+}
+
+impl NodeContainer {
+    /// Return true if [`Self::to_value`] would return a value
+    pub fn will_be_written(&self) -> bool {
+        if !self.modified {
+            return false;
+        }
+
         match &self.node {
-            super::Node::Null => todo!(),
-            super::Node::Bool(node_bool) => ser.serialize_bool(node_bool.value.unwrap()),
-            super::Node::String(node_string) => todo!(),
-            super::Node::Number(node_number) => todo!(),
-            super::Node::Object(node_object) => {
-                let mut map = ser.serialize_struct("", node_object.nodes.len())?;
+            Node::Null => true,
+            Node::Bool(node_bool) => node_bool.value.is_some(),
+            Node::String(node_string) => node_string.value.is_some(),
+            Node::Char(node_char) => node_char.value.is_some(),
+            Node::Number(node_number) => node_number.value.is_some(),
+            Node::Option(node_option) => node_option
+                .enabled
+                .is_some_and(|enabled| !enabled || node_option.inner.will_be_written()),
+            Node::Object(_) => true,
+            Node::Enum(node_enum) => node_enum
+                .value
+                .is_some_and(|pos| node_enum.nodes[pos].will_be_written()),
+            Node::Array(node_array) => node_array
+                .values
+                .as_ref()
+                .is_some_and(|values| values.iter().all(|n| n.will_be_written())),
+            Node::Value(_) => true,
+            Node::Any(node_any) => node_any.value.is_some(),
+        }
+    }
+}
+
+impl NodeContainer {
+    /// Unmark the nodes that have the same value in `baseline`, so only the
+    /// real overrides are written. Structs are compared field by field,
+    /// everything else as a whole.
+    pub fn retain_overrides(&mut self, baseline: &NodeContainer) {
+        if let (Node::Object(node_object), Node::Object(baseline_object)) =
+            (&mut self.node, &baseline.node)
+            && node_object.is_struct()
+        {
+            for (key, node) in &mut node_object.nodes {
+                let Some(baseline) = baseline_object.nodes.get(key) else {
+                    continue;
+                };
+
+                node.retain_overrides(baseline);
+
+                // don't write empty structs
+                if let Node::Object(inner_object) = &node.node
+                    && inner_object.is_struct()
+                    && inner_object.unknown.is_empty()
+                    && !inner_object.nodes.values().any(|n| n.will_be_written())
+                {
+                    node.modified = false;
+                }
+            }
+        } else if self.to_value_full(&Tag::Default) == baseline.to_value_full(&Tag::Default) {
+            self.modified = false;
+        }
+    }
+}
 
-                for (key, val) in &node_object.nodes {
-                    let key: &'static str = Box::leak(key.to_string().into_boxed_str());
+impl TreeSerializer<'_> {
+    fn serialize_variant<S: serde::Serializer>(
+        &self,
+        ser: S,
+        index: u32,
+        name: &'static str,
+        kind: VariantKind,
+        inner: &NodeContainer,
+    ) -> Result<S::Ok, S::Error> {
+        if self.variants_as_maps {
+            let mut map = ser.serialize_map(Some(1))?;
+            map.serialize_entry(name, &self.with(inner))?;
+            return map.end();
+        }
+
+        match (kind, &inner.node) {
+            (VariantKind::Tuple, Node::Array(node_array)) => {
+                let values = value_or_err::<S, _>(node_array.values.as_ref())?;
+
+                let mut tuple = ser.serialize_tuple_variant("", index, name, values.len())?;
+
+                for node in values {
+                    tuple.serialize_field(&self.with(node))?;
+                }
+
+                tuple.end()
+            }
+            (VariantKind::Struct, Node::Object(node_object)) => {
+                let nodes = node_object
+                    .nodes
+                    .iter()
+                    .filter(|(_, node)| node.will_be_written());
+
+                let len = nodes.clone().count() + node_object.unknown.len();
+                let mut map = ser.serialize_struct_variant("", index, name, len)?;
+
+                for (key, node) in nodes {
+                    map.serialize_field(intern(key), &self.with(node))?;
+                }
 
-                    map.serialize_field(key, val)?;
+                for (key, value) in &node_object.unknown {
+                    match node_object.unknown_ron(key) {
+                        Some(value) => map.serialize_field(intern(key), value)?,
+                        None => map.serialize_field(intern(key), value)?,
+                    }
                 }
 
                 map.end()
             }
-            super::Node::Enum(node_enum) => todo!(),
-            super::Node::Array(node_array) => todo!(),
-            super::Node::Value(node_value) => todo!(),
-            super::Node::Any => todo!(),
+            _ => ser.serialize_newtype_variant("", index, name, &self.with(inner)),
+        }
+    }
+}
+
+fn value_or_err<S: serde::Serializer, T>(value: Option<T>) -> Result<T, S::Error> {
+    value.ok_or_else(|| S::Error::custom("no value defined"))
+}
+
+/// Serde require `&'static str` for struct fields.
+/// Leak each key only once.
+pub(crate) fn intern(key: &str) -> &'static str {
+    static KEYS: LazyLock<Mutex<HashSet<&'static str>>> =
+        LazyLock::new(|| Mutex::new(HashSet::new()));
+
+    let mut keys = KEYS.lock().unwrap();
+
+    match keys.get(key) {
+        Some(key) => key,
+        None => {
+            let key: &'static str = Box::leak(key.to_string().into_boxed_str());
+            keys.insert(key);
+            key
         }
     }
 }
@@ -71,4 +330,27 @@ mod test {
     fn test_bool_ron() {
         test_schema::<TestBool>();
     }
+
+    #[test]
+    fn test_option_ron() {
+        test_schema::<TestOption>();
+        test_schema::<TestOptionSome>();
+        test_schema::<TestOptionComplex>();
+    }
+
+    #[test]
+    fn test_enum_ron() {
+        test_schema::<TestEnumSimple>();
+        test_schema::<TestEnumComplex>();
+    }
+
+    #[test]
+    fn test_tuple_ron() {
+        test_schema::<TestTuple>();
+    }
+
+    #[test]
+    fn test_very_complex_ron() {
+        test_schema::<TestVeryComplex>();
+    }
 }
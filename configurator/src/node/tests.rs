@@ -1,14 +1,21 @@
-use crate::{node::NodeContainer, test_common::*};
+use crate::{
+    node::{
+        data_path::DataPathType, effective_default::DefaultSource, Node, NodeContainer, NumberValue,
+    },
+    test_common::*,
+    utils::{figment_value_from_str, figment_value_to_string},
+};
 
 use std::collections::HashMap;
 
+use configurator_utils::ConfigFormat;
 use cosmic::iced_futures::backend::default;
 use figment::{
     providers,
-    value::{Tag, Value},
+    value::{Empty, Tag, Value},
     Figment, Profile,
 };
-use schemars::{schema_for, JsonSchema};
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
 use serde::{Deserialize, Serialize};
 
 /// 1. Generate a node from schema
@@ -99,3 +106,435 @@ fn test_hash_map() {
 fn test_very_complex() {
     test_schema::<TestVeryComplex>(true);
 }
+
+#[test]
+fn test_any() {
+    test_schema::<TestAny>(true);
+}
+
+#[test]
+fn test_option_some() {
+    test_schema::<TestOptionSome>(true);
+}
+
+#[test]
+fn test_option_toggle() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestOptionSome));
+
+    tree.apply_value(Value::serialize(TestOptionSome::default()).unwrap(), false)
+        .unwrap();
+
+    let x = tree
+        .get_at_mut([DataPathType::Name("x".into())].iter())
+        .unwrap();
+
+    let node_option = x.node.unwrap_option_mut();
+    assert_eq!(node_option.enabled, Some(true));
+
+    node_option.enabled = Some(false);
+    assert!(x.is_valid());
+    assert_eq!(
+        x.to_value_full(&Tag::Default),
+        Some(Value::Empty(Tag::Default, Empty::None))
+    );
+
+    // the inner value is kept
+    x.node.unwrap_option_mut().enabled = Some(true);
+    assert_eq!(
+        x.to_value_full(&Tag::Default),
+        Some(Value::serialize(Complex::default()).unwrap())
+    );
+}
+
+#[test]
+fn test_fixed() {
+    test_schema::<TestFixed>(true);
+}
+
+#[test]
+fn test_fixed_len() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestFixed));
+
+    let c = tree
+        .get_at([DataPathType::Name("c".into())].iter())
+        .unwrap();
+    assert!(matches!(c.node, Node::Char(_)));
+
+    let value = |str: &str| figment_value_from_str(str).unwrap();
+
+    assert!(tree.apply_value(value(r#"{ "c": "ab" }"#), true).is_err());
+    assert!(tree
+        .apply_value(value(r#"{ "color": [1, 2] }"#), true)
+        .is_err());
+    assert!(tree
+        .apply_value(value(r#"{ "rect": [1, 2, 3, 4, 5] }"#), true)
+        .is_err());
+    tree.apply_value(value(r#"{ "c": "y", "rect": [1, 2, 3, 4] }"#), true)
+        .unwrap();
+
+    // the values of fixed-size arrays can't be removed
+    let rect = tree
+        .get_at_mut([DataPathType::Name("rect".into())].iter())
+        .unwrap();
+    let node_array = rect.node.unwrap_array_mut();
+    assert!(!node_array.can_add());
+    assert!(!node_array.can_remove());
+    assert!(!node_array.values.as_ref().unwrap()[0].removable);
+
+    // a tuple without default gets all its values
+    let color = tree
+        .get_at_mut([DataPathType::Name("color".into())].iter())
+        .unwrap();
+    color.default = None;
+    color.remove_value_rec();
+    color.init_with_defaults(true).unwrap();
+    assert_eq!(
+        color.node.unwrap_array_mut().values.as_ref().unwrap().len(),
+        3
+    );
+}
+
+#[test]
+fn test_enum_select_variant() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestEnumComplex));
+
+    tree.apply_value(Value::serialize(TestEnumComplex::default()).unwrap(), false)
+        .unwrap();
+
+    let x = tree
+        .get_at_mut([DataPathType::Name("x".into())].iter())
+        .unwrap();
+
+    let variant = |x: &NodeContainer| x.to_value_full(&Tag::Default).unwrap();
+
+    let pos_b = 1;
+    let pos_c = 2;
+    let value_c = variant(x);
+
+    x.select_variant(pos_b).unwrap();
+    assert!(!x.is_valid());
+
+    let node_b = x.node.unwrap_enum_mut().variant_mut(pos_b);
+    node_b
+        .apply_value(Value::serialize(EnumComplex::B(7)).unwrap(), true)
+        .unwrap();
+    let value_b = variant(x);
+
+    // earlier values of the variants are remembered
+    x.select_variant(pos_c).unwrap();
+    assert_eq!(variant(x), value_c);
+    x.select_variant(pos_b).unwrap();
+    assert_eq!(variant(x), value_b);
+
+    // a fresh variant is initialized with the default of the enum
+    x.remove_value_rec();
+    x.select_variant(pos_c).unwrap();
+    assert_eq!(variant(x), value_c);
+    assert!(x.to_value(&Tag::Default).is_some());
+}
+
+#[test]
+fn test_retain_overrides() {
+    let mut baseline = NodeContainer::from_json_schema(&schema_for!(TestNested));
+    baseline
+        .apply_value(Value::serialize(TestNested::default()).unwrap(), true)
+        .unwrap();
+
+    let mut tree = baseline.clone();
+    let y = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("a".into()),
+                DataPathType::Name("y".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    y.apply_value(Value::from(5), true).unwrap();
+
+    tree.retain_overrides(&baseline);
+
+    assert_eq!(
+        json::to_value(tree.to_value(&Tag::Default).unwrap()).unwrap(),
+        json::json!({ "a": { "y": 5 } })
+    );
+}
+
+#[test]
+fn test_update_value() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestVeryComplex));
+
+    let config = |y: EnumComplex| {
+        let Value::Dict(tag, mut dict) = Value::serialize(TestVeryComplex::default()).unwrap()
+        else {
+            panic!()
+        };
+        dict.insert("y".into(), Value::serialize(y).unwrap());
+        Value::Dict(tag, dict)
+    };
+
+    tree.update_value(config(EnumComplex::B(1)), true).unwrap();
+
+    let y = tree
+        .get_at_mut([DataPathType::Name("y".into())].iter())
+        .unwrap();
+    let (pos_b, _) = y.node.unwrap_enum_mut().unwrap_value();
+
+    // remember a value for the variant C, and a number being typed
+    y.select_variant(2).unwrap();
+    assert!(y.node.unwrap_enum_mut().nodes[2].has_value());
+    y.select_variant(pos_b).unwrap();
+    let number = y
+        .get_at_mut([DataPathType::Indice(pos_b), DataPathType::Name("B".into())].iter())
+        .unwrap();
+    number.node.unwrap_number_mut().value_string = "01".into();
+
+    // unchanged nodes are kept as is
+    tree.update_value(config(EnumComplex::B(1)), true).unwrap();
+
+    let y = tree
+        .get_at_mut([DataPathType::Name("y".into())].iter())
+        .unwrap();
+    assert!(y.node.unwrap_enum_mut().nodes[2].has_value());
+    let number = y
+        .get_at_mut([DataPathType::Indice(pos_b), DataPathType::Name("B".into())].iter())
+        .unwrap();
+    assert_eq!(number.node.unwrap_number_mut().value_string, "01");
+
+    // changed nodes are updated
+    tree.update_value(config(EnumComplex::B(2)), true).unwrap();
+
+    let number = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("y".into()),
+                DataPathType::Indice(pos_b),
+                DataPathType::Name("B".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    assert_eq!(number.node.unwrap_number_mut().value_string, "2");
+
+    let mut expected = NodeContainer::from_json_schema(&schema_for!(TestVeryComplex));
+    expected
+        .apply_value(config(EnumComplex::B(2)), true)
+        .unwrap();
+    assert_eq!(
+        tree.to_value(&Tag::Default),
+        expected.to_value(&Tag::Default)
+    );
+}
+
+#[test]
+fn test_number_precision() {
+    let schema = schema_for!(TestPrecision);
+
+    let mut tree = NodeContainer::from_json_schema(&schema);
+
+    tree.apply_value(Value::serialize(TestPrecision::default()).unwrap(), true)
+        .unwrap();
+
+    let number = |name: &str| {
+        tree.get_at([DataPathType::Name(name.into())].iter())
+            .unwrap()
+            .node
+            .clone()
+            .unwrap_number()
+    };
+
+    let x = number("x");
+    assert_eq!(x.value_string, "0.12345");
+    assert_eq!(
+        x.value.as_ref().unwrap().to_string_exact(),
+        "0.123450003564357757568359375"
+    );
+    assert_eq!(number("y").value_string, "0.30000000000000004");
+    assert_eq!(number("z").value_string, u128::MAX.to_string());
+
+    assert!(matches!(
+        x.try_parse_from_str("1.5e-2"),
+        Ok(NumberValue::F32(0.015))
+    ));
+    assert!(matches!(
+        number("z").try_parse_from_str("0xff"),
+        Ok(NumberValue::U128(255))
+    ));
+}
+
+#[test]
+fn test_effective_default() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestParentDefault));
+
+    let path = [
+        DataPathType::Name("nested".into()),
+        DataPathType::Name("number".into()),
+    ];
+
+    let number = |tree: &NodeContainer| {
+        tree.get_at(path.iter())
+            .unwrap()
+            .to_value_full(&Tag::Default)
+    };
+
+    // `nested` is missing, so the default of the parent applies
+    tree.apply_value(figment_value_from_str("{}").unwrap(), true)
+        .unwrap();
+    let default = tree.effective_default(&path).unwrap();
+    assert_eq!(default.source, DefaultSource::Ancestor(1));
+    assert_eq!(Some(default.value.clone()), number(&tree));
+    assert_eq!(default.value.to_u128(), Some(0));
+
+    // `nested` is there, so the default of the child applies
+    tree.remove_value_rec();
+    tree.apply_value(figment_value_from_str(r#"{ "nested": {} }"#).unwrap(), true)
+        .unwrap();
+    let default = tree.effective_default(&path).unwrap();
+    assert_eq!(default.source, DefaultSource::Own);
+    assert_eq!(Some(default.value.clone()), number(&tree));
+    assert_eq!(default.value.to_u128(), Some(1));
+}
+
+#[test]
+fn test_search() {
+    let schema = schema_for!(TestVeryComplex);
+
+    let mut tree = NodeContainer::from_json_schema(&schema);
+
+    let figment = Figment::new().join(providers::Serialized::from(
+        TestVeryComplex::default(),
+        Profile::Default,
+    ));
+
+    tree.apply_figment(&figment).unwrap();
+
+    let paths = |query: &str| {
+        tree.search(query)
+            .into_iter()
+            .map(|result| {
+                result
+                    .data_path
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("/")
+            })
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(paths("HELLO"), vec!["x/hello", "y/2/C/x", "v/1/lol/2/C/x"]);
+    assert_eq!(paths("bis"), vec!["v/0/1/3/D/b/x"]);
+    assert!(paths("").is_empty());
+}
+
+/// Copy the whole tree with a format, and paste it to a new tree
+fn test_copy_paste<S: JsonSchema + Default + Serialize>(format: &ConfigFormat) {
+    let schema = schema_for!(S);
+
+    let mut tree = NodeContainer::from_json_schema(&schema);
+
+    let figment = Figment::new().join(providers::Serialized::from(S::default(), Profile::Default));
+
+    tree.apply_figment(&figment).unwrap();
+
+    let copied = tree.to_value_full(&Tag::Default).unwrap();
+    let content = figment_value_to_string(&copied, format).unwrap();
+
+    let mut new_tree = NodeContainer::from_json_schema(&schema);
+    new_tree
+        .apply_value(figment_value_from_str(&content).unwrap(), true)
+        .unwrap();
+
+    assert_eq!(new_tree.to_value(&Tag::Default).unwrap(), copied);
+}
+
+#[test]
+fn test_copy_paste_json() {
+    test_copy_paste::<TestVeryComplex>(&ConfigFormat::Json);
+}
+
+#[test]
+fn test_copy_paste_ron() {
+    test_copy_paste::<TestVeryComplex>(&ConfigFormat::CosmicRon);
+}
+
+#[test]
+fn test_any_parse_error() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestAny));
+
+    let node_any = tree
+        .get_at_mut([DataPathType::Name("x".into())].iter())
+        .unwrap()
+        .node
+        .unwrap_any_mut();
+
+    assert!(node_any.set_value_string("[1, 2]".into()));
+    assert!(node_any.error.is_none());
+
+    // the last valid value is kept
+    assert!(!node_any.set_value_string("[1, ".into()));
+    assert!(node_any.error.is_some());
+    assert_eq!(node_any.value_string, "[1, ");
+    assert_eq!(node_any.value, figment_value_from_str("[1, 2]").ok());
+}
+
+#[test]
+fn test_cosmic_compat_schemas() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../cosmic_compat/schemas");
+
+    for dir_entry in std::fs::read_dir(dir).unwrap() {
+        let path = dir_entry.unwrap().path();
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        let schema = json::from_str::<RootSchema>(&content)
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        NodeContainer::from_json_schema(&schema);
+    }
+
+    // floats in the validation of a schema
+    let schema = json::from_str::<RootSchema>(r#"{ "type": "number", "minimum": 1e3 }"#).unwrap();
+    NodeContainer::from_json_schema(&schema);
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, Default)]
+struct PartialStruct {
+    a: i32,
+    #[serde(default)]
+    b: i32,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(untagged)]
+enum UntaggedTuple {
+    Tuple((PartialStruct, i32)),
+    Text(String),
+}
+
+#[test]
+fn test_match_tuple_with_missing_field() {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(UntaggedTuple));
+
+    tree.apply_value(figment_value_from_str(r#"[{ "a": 1 }, 2]"#).unwrap(), true)
+        .unwrap();
+
+    let value = tree.to_value(&Tag::Default).unwrap();
+    assert!(matches!(value, Value::Array(_, values) if values.len() == 2));
+}
+
+#[test]
+fn test_localized_keys_only_for_keyfiles() {
+    let value = figment_value_from_str(r#"{ "x": "a", "y": 1, "y[0]": "b" }"#).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(Complex));
+    tree.apply_value(value.clone(), true).unwrap();
+
+    let node_object = tree.node.unwrap_object_mut();
+    assert!(!node_object.nodes.contains_key("y[0]"));
+    assert!(node_object.unknown.contains_key("y[0]"));
+
+    // the value doesn't fit the node of `y`
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(Complex));
+    tree.enable_localized_keys();
+    tree.apply_value(value, true).unwrap();
+    assert!(tree.node.unwrap_object_mut().unknown.contains_key("y[0]"));
+}
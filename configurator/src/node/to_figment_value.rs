@@ -10,7 +10,17 @@ use super::{from_json_schema::json_value_to_figment_value, NodeContainer, Number
 impl NodeContainer {
     // todo: return a result with info about the node missing a value
     pub fn to_value(&self, tag: &Tag) -> Option<Value> {
-        if !self.modified {
+        self.to_value_impl(tag, true)
+    }
+
+    /// Same as [`Self::to_value`], but include nodes that are not modified.
+    /// This is the value currently displayed by the UI.
+    pub fn to_value_full(&self, tag: &Tag) -> Option<Value> {
+        self.to_value_impl(tag, false)
+    }
+
+    fn to_value_impl(&self, tag: &Tag, only_modified: bool) -> Option<Value> {
+        if only_modified && !self.modified {
             return None;
         }
 
@@ -21,34 +31,47 @@ impl NodeContainer {
                 .value
                 .as_ref()
                 .map(|value| Value::String(*tag, value.clone())),
+            Node::Char(node_char) => node_char.value.map(|value| Value::Char(*tag, value)),
             Node::Number(node_number) => node_number
                 .value
                 .as_ref()
                 .map(|value| Value::Num(*tag, value.clone().into_num())),
+            Node::Option(node_option) => node_option.enabled.and_then(|enabled| {
+                if enabled {
+                    node_option.inner.to_value_impl(tag, only_modified)
+                } else {
+                    Some(Value::Empty(*tag, Empty::None))
+                }
+            }),
             Node::Object(node_object) => {
                 let mut dict = Dict::new();
 
                 for (key, node) in &node_object.nodes {
-                    if let Some(value) = node.to_value(tag) {
+                    if let Some(value) = node.to_value_impl(tag, only_modified) {
                         dict.insert(key.clone(), value);
                     }
                 }
+
+                for (key, value) in &node_object.unknown {
+                    dict.insert(key.clone(), value.clone());
+                }
                 Some(Value::Dict(*tag, dict))
             }
             Node::Enum(node_enum) => node_enum.value.and_then(|pos| {
-                node_enum.nodes[pos].to_value(tag)
+                node_enum.nodes[pos].to_value_impl(tag, only_modified)
 
                 // Value::Dict(tag.clone(), Dict::new());
                 // todo!()
             }),
-            Node::Array(node_array) => node_array.values.as_ref().map(|values| {
-                Value::Array(
-                    *tag,
-                    values.iter().map(|n| n.to_value(tag).unwrap()).collect(),
-                )
+            Node::Array(node_array) => node_array.values.as_ref().and_then(|values| {
+                values
+                    .iter()
+                    .map(|n| n.to_value_impl(tag, only_modified))
+                    .collect::<Option<Vec<_>>>()
+                    .map(|values| Value::Array(*tag, values))
             }),
             Node::Value(node_value) => Some(json_value_to_figment_value(&node_value.value)),
-            Node::Any => todo!(),
+            Node::Any(node_any) => node_any.value.clone(),
         }
     }
 }
@@ -1,4 +1,5 @@
 use std::{
+    borrow::Cow,
     fs::{self, File},
     io::Read,
     iter::{self},
@@ -20,9 +21,16 @@ use xdg::BaseDirectories;
 
 use crate::{
     app::{self, Dialog},
+    backup::{self, Backup},
     config::Config,
     message::{ChangeMsg, PageMsg},
-    node::{data_path::DataPath, Node, NodeContainer, NumberValue},
+    node::{
+        data_path::{DataPath, DataPathType},
+        effective_default::DefaultSource,
+        Node, NodeContainer, NumberValue,
+    },
+    providers::{cosmic_ron, ron_value::RonValue},
+    utils::{figment_value_from_str, figment_value_to_string},
 };
 
 use configurator_utils::ConfigFormat;
@@ -36,13 +44,43 @@ pub struct Page {
     pub source_home_path: PathBuf,
     pub write_path: PathBuf,
     pub format: ConfigFormat,
+    /// Version of the config expected by the schema, for the versioned formats (`cosmic_ron`)
+    pub version: Option<u64>,
+    /// Version of the user config read on disk
+    pub disk_version: Option<u64>,
+    /// Files of the user config that could not be parsed. Their keys are not loaded
+    pub file_errors: Vec<cosmic_ron::FileError>,
+    /// Error of the last reload. The config is then only partly loaded,
+    /// and is not written: the values that were not loaded would be lost
+    pub load_error: Option<String>,
+
+    pub backup_count: usize,
+    /// The most recent first
+    pub backups: Vec<Backup>,
+    /// The config has been saved before the first write of this session
+    pub backed_up: bool,
 
     pub system_config: Figment,
+    /// The system configs as they were written, for the RON formats
+    pub system_ron: Vec<RonValue>,
     pub user_config: Figment,
     pub full_config: Figment,
 
+    pub write_mode: WriteMode,
+
     pub tree: NodeContainer,
     pub data_path: DataPath,
+    /// Position of the value being dragged in the current node
+    pub dragged: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WriteMode {
+    /// Write every modified value
+    #[default]
+    Modified,
+    /// Only write the values that differ from the system config and the defaults of the schema
+    Overrides,
 }
 
 pub fn create_pages(config: &Config) -> impl Iterator<Item = Page> + use<'_> {
@@ -70,7 +108,7 @@ pub fn create_pages(config: &Config) -> impl Iterator<Item = Page> + use<'_> {
                 let appid = appid_from_schema_path(file.path());
 
                 if !config.masked.contains(&appid) {
-                    Some(Page::from_str(&appid, content).unwrap())
+                    Some(Page::from_str(&appid, content, config).unwrap())
                 } else {
                     None
                 }
@@ -106,7 +144,7 @@ pub fn create_pages(config: &Config) -> impl Iterator<Item = Page> + use<'_> {
 
             if !config.masked.contains(&appid) {
                 match fs::read_to_string(&schema_path) {
-                    Ok(content) => match Page::from_str(&appid, &content) {
+                    Ok(content) => match Page::from_str(&appid, &content, config) {
                         Ok(page) => Some(page),
                         Err(e) => {
                             error!("{}", e);
@@ -125,6 +163,14 @@ pub fn create_pages(config: &Config) -> impl Iterator<Item = Page> + use<'_> {
         .chain(cosmic_compat(config))
 }
 
+/// The directory of the version to read, for the versioned formats
+fn read_path(path: &Path, format: &ConfigFormat, version: Option<u64>) -> PathBuf {
+    match format {
+        ConfigFormat::CosmicRon => cosmic_ron::version_path(path, version),
+        _ => path.to_path_buf(),
+    }
+}
+
 fn appid_from_schema_path(schema_path: &Path) -> String {
     let schema_name = schema_path.file_name().unwrap().to_string_lossy();
 
@@ -134,8 +180,8 @@ fn appid_from_schema_path(schema_path: &Path) -> String {
 
 impl Page {
     // need &str for appid: https://github.com/tokio-rs/tracing/issues/1181
-    #[instrument(skip(content))]
-    fn from_str(appid: &str, content: &str) -> anyhow::Result<Self> {
+    #[instrument(skip(content, config))]
+    fn from_str(appid: &str, content: &str, config: &Config) -> anyhow::Result<Self> {
         let json_value = json::Value::from_str(content)?;
 
         let Some(json_obj) = json_value.as_object() else {
@@ -183,14 +229,32 @@ impl Page {
 
         let format = ConfigFormat::try_from(format)?;
 
+        let version = match json_obj.get("X_CONFIGURATOR_VERSION") {
+            Some(version) => Some(
+                version
+                    .as_u64()
+                    .ok_or_else(|| anyhow!("X_CONFIGURATOR_VERSION is not a positive integer"))?,
+            ),
+            None => None,
+        };
+
+        info!("start generating node from schema");
+        let mut tree = NodeContainer::from_json_schema(&json::from_value(json_value)?);
+
+        if format == ConfigFormat::Keyfile {
+            tree.enable_localized_keys();
+        }
+
         let mut system_config = Figment::new();
+        let mut system_ron = Vec::new();
 
         for path in &source_paths {
-            system_config = system_config.merge(crate::providers::read_from_format(path, &format))
-        }
+            let read =
+                crate::providers::read_config(read_path(path, &format, version), &format, &tree);
 
-        info!("start generating node from schema");
-        let tree = NodeContainer::from_json_schema(&json::from_value(json_value)?);
+            system_config = system_config.merge(read.provider);
+            system_ron.extend(read.ron);
+        }
 
         let title = appid.split('.').last().unwrap().to_string();
 
@@ -198,14 +262,32 @@ impl Page {
             title,
             appid: appid.to_string(),
             system_config,
+            system_ron,
             user_config: Figment::new(),
             full_config: Figment::new(),
+            write_mode: if config.overrides_only.iter().any(|a| a == appid) {
+                WriteMode::Overrides
+            } else {
+                WriteMode::Modified
+            },
             tree,
             data_path: DataPath::new(),
+            dragged: None,
             source_paths,
             source_home_path,
             write_path,
             format,
+            version,
+            disk_version: None,
+            file_errors: Vec::new(),
+            load_error: None,
+            backup_count: config
+                .backup_counts
+                .get(appid)
+                .copied()
+                .unwrap_or(config.backup_count),
+            backups: Vec::new(),
+            backed_up: false,
         };
 
         if let Err(err) = page.reload() {
@@ -223,10 +305,32 @@ impl Page {
     pub fn reload(&mut self) -> anyhow::Result<()> {
         info!("reload the config");
 
-        self.user_config = Figment::new().merge(crate::providers::read_from_format(
-            &self.source_home_path,
-            &self.format,
-        ));
+        let res = self.load();
+        self.load_error = res.as_ref().err().map(|err| format!("{err:#}"));
+        res
+    }
+
+    fn load(&mut self) -> anyhow::Result<()> {
+        let home_path = read_path(&self.source_home_path, &self.format, self.version);
+
+        self.disk_version = match self.format {
+            ConfigFormat::CosmicRon => cosmic_ron::version_of_path(&home_path),
+            _ => None,
+        };
+
+        if let Some(version) = self.version
+            && let Some(disk_version) = self.disk_version
+            && disk_version != version
+        {
+            warn!(
+                "the config on disk is the version {disk_version}, but the schema expects the version {version}"
+            );
+        }
+
+        let read = crate::providers::read_config(&home_path, &self.format, &self.tree);
+
+        self.file_errors = read.file_errors;
+        self.user_config = Figment::new().merge(read.provider);
 
         debug!("user_config = {:#?}", self.user_config);
 
@@ -239,31 +343,132 @@ impl Page {
         // debug!("tree = {:#?}", self.tree);
         debug!("full_config = {:#?}", self.full_config);
 
-        self.tree.remove_value_rec();
+        self.tree.update_figment(&self.full_config)?;
 
-        self.tree.apply_figment(&self.full_config)?;
+        // in the order of the figment, so the user config wins
+        for value in self.system_ron.iter().chain(&read.ron) {
+            self.tree.keep_unknown_ron(value);
+        }
 
         self.data_path.sanitize_path(&self.tree);
 
+        self.backups = backup::backups_dir(&self.appid)
+            .map(|dir| backup::list(&dir))
+            .unwrap_or_default();
+
         Ok(())
     }
 
-    pub fn write(&self) -> anyhow::Result<()> {
-        match self.tree.to_value(&Tag::Default) {
-            Some(value) => {
-                crate::providers::write(&self.write_path, &self.format, &value)?;
+    pub fn write(&mut self) -> anyhow::Result<()> {
+        if let Some(err) = &self.load_error {
+            bail!("the config is not written because it could not be fully loaded: {err}");
+        }
+
+        let write_path = self.versioned_write_path();
+
+        // the config as it was before the changes of this session
+        if !self.backed_up {
+            self.backup(&write_path);
+            self.backed_up = true;
+        }
+
+        let tree = match self.write_mode {
+            WriteMode::Modified => Cow::Borrowed(&self.tree),
+            WriteMode::Overrides => {
+                let mut tree = self.tree.clone();
+                tree.retain_overrides(&self.layered_default());
+                Cow::Owned(tree)
             }
-            None => bail!("no value to write"),
+        };
+
+        if !tree.will_be_written() {
+            bail!("no value to write");
         }
 
+        crate::providers::write(&write_path, &self.format, &tree, &self.file_errors)?;
+
         Ok(())
     }
+
+    fn versioned_write_path(&self) -> PathBuf {
+        match self.format {
+            ConfigFormat::CosmicRon => {
+                cosmic_ron::write_version_path(&self.write_path, self.version)
+            }
+            _ => self.write_path.clone(),
+        }
+    }
+
+    /// A failed backup doesn't prevent writing the config
+    fn backup(&mut self, path: &Path) {
+        let res = backup::backups_dir(&self.appid).and_then(|dir| {
+            backup::backup(&dir, path, self.backup_count)?;
+            Ok(backup::list(&dir))
+        });
+
+        match res {
+            Ok(backups) => self.backups = backups,
+            Err(err) => error!("can't backup {}: {err}", path.display()),
+        }
+    }
+
+    pub fn restore(&mut self, backup: &Backup) -> anyhow::Result<()> {
+        let write_path = self.versioned_write_path();
+
+        // before the backup below, which can remove it
+        let content = backup::read(backup)?;
+
+        // so the restore can be undone
+        self.backup(&write_path);
+
+        backup::restore(content, &write_path)?;
+
+        self.reload()
+    }
+
+    /// Replace the value of the node with its effective default
+    fn apply_default(&mut self, data_path: &[DataPathType]) -> anyhow::Result<()> {
+        let Some(default) = self.tree.effective_default(data_path) else {
+            return Ok(());
+        };
+        let (default, source) = (default.value.clone(), default.source);
+
+        let node = self.tree.get_at_mut(data_path.iter()).unwrap();
+
+        node.remove_value_rec();
+        node.apply_value(default, false)?;
+
+        // the default of a missing ancestor only applies while it stays missing
+        if source == DefaultSource::Own && !data_path.is_empty() {
+            self.tree
+                .set_modified(data_path[..data_path.len() - 1].iter());
+        }
+
+        Ok(())
+    }
+
+    /// The values the page would have without any user config:
+    /// the system config, on top of the defaults of the schema
+    fn layered_default(&self) -> NodeContainer {
+        let mut tree = self.tree.clone();
+        tree.remove_value_rec();
+
+        if let Err(err) = tree.apply_figment(&self.system_config) {
+            error!("{err}");
+        }
+
+        tree
+    }
 }
 
 #[must_use]
 pub enum Action {
     CreateDialog(Dialog),
     RemoveDialog,
+    WriteClipboard(String),
+    ReadClipboard(Vec<DataPathType>),
+    /// Remember the write mode of this page in the config
+    SaveWriteMode(WriteMode),
     None,
 }
 
@@ -273,22 +478,40 @@ impl Page {
 
         match message {
             PageMsg::SelectDataPath(pos) => {
+                self.dragged.take();
                 self.data_path.change_to(pos);
             }
             PageMsg::OpenDataPath(data_path_type) => {
+                self.dragged.take();
                 self.data_path.open(data_path_type);
             }
+            PageMsg::DragStart(pos) => {
+                self.dragged.replace(pos);
+            }
+            PageMsg::DragEnd => {
+                self.dragged.take();
+            }
+            PageMsg::Drop(to) => {
+                if let Some(from) = self.dragged.take()
+                    && from != to
+                {
+                    return self.update(
+                        PageMsg::ChangeMsg(
+                            self.data_path.current().to_vec(),
+                            ChangeMsg::Move { from, to },
+                        ),
+                        page_id,
+                    );
+                }
+            }
             PageMsg::ChangeMsg(data_path, change_msg) => {
                 let node = self.tree.get_at_mut(data_path.iter()).unwrap();
 
                 match change_msg {
                     ChangeMsg::ApplyDefault => {
-                        node.remove_value_rec();
-                        node.apply_value(node.default.clone().unwrap(), false)
-                            .unwrap();
-
-                        self.tree
-                            .set_modified(data_path[..data_path.len() - 1].iter());
+                        if let Err(err) = self.apply_default(&data_path) {
+                            return Action::CreateDialog(Dialog::Error(err.to_string()));
+                        }
                     }
                     ChangeMsg::ChangeBool(value) => {
                         let node_bool = node.node.unwrap_bool_mut();
@@ -300,6 +523,11 @@ impl Page {
                         node_string.value = Some(value);
                         self.tree.set_modified(data_path.iter());
                     }
+                    ChangeMsg::ChangeChar(value) => {
+                        let node_char = node.node.unwrap_char_mut();
+                        node_char.value = Some(value);
+                        self.tree.set_modified(data_path.iter());
+                    }
                     ChangeMsg::ChangeNumber(value) => {
                         let node_number = node.node.unwrap_number_mut();
                         node_number.value_string = value;
@@ -315,11 +543,47 @@ impl Page {
 
                         self.tree.set_modified(data_path.iter());
                     }
+                    ChangeMsg::NumberFullPrecision(full) => {
+                        let node_number = node.node.unwrap_number_mut();
+
+                        // only the display changes, the value is the same
+                        if let Some(value) = &node_number.value {
+                            node_number.value_string = if full {
+                                value.to_string_exact()
+                            } else {
+                                value.to_string()
+                            };
+                        }
+                    }
+                    ChangeMsg::ChangeAny(value) => {
+                        let node_any = node.node.unwrap_any_mut();
+
+                        if !node_any.set_value_string(value) {
+                            return Action::None;
+                        }
+
+                        self.tree.set_modified(data_path.iter());
+                    }
                     ChangeMsg::ChangeEnum(value) => {
-                        let node_enum = node.node.unwrap_enum_mut();
-                        node_enum.value = Some(value);
+                        if let Err(err) = node.select_variant(value) {
+                            return Action::CreateDialog(Dialog::Error(err.to_string()));
+                        }
+                        self.tree.set_modified(data_path.iter());
+                    }
+                    ChangeMsg::ChangeOption(value) => {
+                        let node_option = node.node.unwrap_option_mut();
+                        node_option.enabled = Some(value);
+
+                        if value {
+                            // the previous inner value is kept, if any
+                            if !node_option.inner.is_valid()
+                                && let Err(err) = node_option.inner.init_with_defaults(true)
+                            {
+                                return Action::CreateDialog(Dialog::Error(err.to_string()));
+                            }
+                            node_option.inner.set_modified_rec();
+                        }
 
-                        node_enum.nodes[value].modified = true;
                         self.tree.set_modified(data_path.iter());
                     }
                     ChangeMsg::Remove(field) => {
@@ -332,6 +596,10 @@ impl Page {
                                 }
                             }
                             Node::Array(node_array) => {
+                                if !node_array.can_remove() {
+                                    return Action::None;
+                                }
+
                                 node_array
                                     .values
                                     .as_mut()
@@ -348,6 +616,22 @@ impl Page {
 
                         self.tree.set_modified(data_path.iter());
                     }
+                    ChangeMsg::RemoveUnknown(key) => {
+                        let node_object = node.node.unwrap_object_mut();
+
+                        match key {
+                            Some(key) => {
+                                node_object.unknown.remove(&key);
+                                node_object.unknown_ron.remove(&key);
+                            }
+                            None => {
+                                node_object.unknown.clear();
+                                node_object.unknown_ron.clear();
+                            }
+                        }
+
+                        self.tree.set_modified(data_path.iter());
+                    }
                     ChangeMsg::AddNewNodeToObject(name) => {
                         let node_object = node.node.unwrap_object_mut();
 
@@ -378,6 +662,10 @@ impl Page {
                     ChangeMsg::AddNewNodeToArray => {
                         let node_array = node.node.unwrap_array_mut();
 
+                        if !node_array.can_add() {
+                            return Action::None;
+                        }
+
                         let mut new_node = node_array.template(None);
 
                         if let Some(default) = &new_node.default {
@@ -412,12 +700,148 @@ impl Page {
                         self.tree.set_modified(data_path.iter());
                         action = Action::RemoveDialog;
                     }
+                    ChangeMsg::Move { from, to } => {
+                        let len = match &node.node {
+                            Node::Object(node_object) => node_object.nodes.len(),
+                            Node::Array(node_array) => {
+                                node_array.values.as_ref().map_or(0, Vec::len)
+                            }
+                            _ => 0,
+                        };
+
+                        // the node changed since the drag started
+                        if from >= len || to >= len {
+                            error!("can't move the value {from} to {to} in {data_path:?}");
+                            return Action::None;
+                        }
+
+                        match &mut node.node {
+                            Node::Object(node_object) => {
+                                node_object.nodes.move_index(from, to);
+
+                                for n in node_object.nodes.values_mut() {
+                                    n.modified = true;
+                                }
+                            }
+                            Node::Array(node_array) => {
+                                let values = node_array.values.as_mut().unwrap();
+
+                                let value = values.remove(from);
+                                values.insert(to, value);
+
+                                for n in values {
+                                    n.modified = true;
+                                }
+                            }
+                            _ => {
+                                error!(
+                                    "can't move a value of {data_path:?}: not an array or a map"
+                                );
+                                return Action::None;
+                            }
+                        }
+
+                        self.tree.set_modified(data_path.iter());
+                    }
+                    ChangeMsg::Duplicate(field) => {
+                        match &mut node.node {
+                            Node::Object(node_object) => {
+                                let key = field.unwrap_name();
+
+                                let (pos, _, value) = node_object.nodes.get_full(&key).unwrap();
+                                let value = value.clone();
+
+                                let mut new_key = format!("{key}_copy");
+                                let mut n = 1;
+                                while node_object.nodes.contains_key(&new_key) {
+                                    n += 1;
+                                    new_key = format!("{key}_copy{n}");
+                                }
+
+                                node_object.nodes.shift_insert(pos + 1, new_key, value);
+
+                                for n in node_object.nodes.values_mut() {
+                                    n.modified = true;
+                                }
+                            }
+                            Node::Array(node_array) => {
+                                if !node_array.can_add() {
+                                    return Action::None;
+                                }
+
+                                let pos = field.unwrap_indice();
+                                let values = node_array.values.as_mut().unwrap();
+
+                                values.insert(pos + 1, values[pos].clone());
+
+                                for n in values {
+                                    n.modified = true;
+                                }
+                            }
+                            _ => {
+                                error!(
+                                    "can't duplicate a value of {data_path:?}: not an array or a map"
+                                );
+                                return Action::None;
+                            }
+                        }
+
+                        self.tree.set_modified(data_path.iter());
+                    }
+                    ChangeMsg::Paste(content) => {
+                        let mut new_node = node.clone();
+
+                        let res = figment_value_from_str(&content).and_then(|value| {
+                            new_node.remove_value_rec();
+                            new_node.apply_value(value, true).map_err(|e| {
+                                e.context("the pasted value doesn't match the schema of this node")
+                            })?;
+
+                            if !new_node.is_valid() {
+                                bail!("the pasted value doesn't define all the required values");
+                            }
+
+                            Ok(())
+                        });
+
+                        if let Err(err) = res {
+                            error!("{err:#}");
+                            return Action::CreateDialog(Dialog::Error(err.to_string()));
+                        }
+
+                        *node = new_node;
+                        self.tree.set_modified(data_path.iter());
+                    }
                 }
 
                 self.data_path.sanitize_path(&self.tree);
 
-                if self.tree.is_valid() {
-                    self.write().unwrap();
+                if self.tree.is_valid()
+                    && let Err(err) = self.write()
+                {
+                    error!("{err:#}");
+                    return Action::CreateDialog(Dialog::Error(err.to_string()));
+                }
+            }
+            PageMsg::SetWriteMode(write_mode) => {
+                // the next change writes the config in this mode
+                self.write_mode = write_mode;
+
+                return Action::SaveWriteMode(write_mode);
+            }
+            PageMsg::RestoreBackup(path) => {
+                let Some(backup) = self
+                    .backups
+                    .iter()
+                    .find(|backup| backup.path == path)
+                    .cloned()
+                else {
+                    return Action::None;
+                };
+
+                if let Err(err) = self.restore(&backup) {
+                    error!("{err}");
+                    return Action::CreateDialog(Dialog::Error(err.to_string()));
                 }
             }
             PageMsg::None => {
@@ -430,6 +854,20 @@ impl Page {
                     page_id,
                 });
             }
+            PageMsg::Copy(data_path) => {
+                let node = self.tree.get_at(data_path.iter()).unwrap();
+
+                match node.to_value_full(&Tag::Default) {
+                    Some(value) => match figment_value_to_string(&value, &self.format) {
+                        Ok(content) => return Action::WriteClipboard(content),
+                        Err(err) => error!("{err}"),
+                    },
+                    None => warn!("no value to copy"),
+                }
+            }
+            PageMsg::Paste(data_path) => {
+                return Action::ReadClipboard(data_path);
+            }
             PageMsg::DialogRenameKey(data_path, key) => {
                 return Action::CreateDialog(Dialog::RenameKey {
                     previous: key.clone(),
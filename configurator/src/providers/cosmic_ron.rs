@@ -1,13 +1,17 @@
 use std::{
+    collections::BTreeMap,
+    fmt::Display,
     fs,
     path::{Path, PathBuf},
 };
 
-use anyhow::{anyhow, bail};
-use figment::{value::Dict, Figment, Metadata, Profile, Provider};
-use ron::Map;
+use figment::{value::Dict, Metadata, Profile, Provider};
+
+use crate::node::{Node, NodeContainer};
 use serde::de::Error;
 
+use super::ron_value::RonValue;
+
 pub struct CosmicRonProvider {
     path: PathBuf,
 }
@@ -28,8 +32,6 @@ impl Provider for CosmicRonProvider {
     fn data(
         &self,
     ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
-        // dbg!(&map);
-
         self.data_impl().map_err(figment::Error::custom)
     }
 }
@@ -38,61 +40,288 @@ impl CosmicRonProvider {
     fn data_impl(
         &self,
     ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
-        // let version = {
-        //     let mut max: Option<u64> = None;
-
-        //     for dir_entry in fs::read_dir(&self.path)? {
-        //         if let Some(filename) = dir_entry?.file_name().to_str() {
-        //             if let Some(version) = filename.strip_prefix('v') {
-        //                 if let Ok(version) = version.parse::<u64>() {
-        //                     max = match max {
-        //                         Some(old) => {
-        //                             if old < version {
-        //                                 Some(version)
-        //                             } else {
-        //                                 Some(old)
-        //                             }
-        //                         }
-        //                         None => Some(version),
-        //                     };
-        //                 }
-        //             }
-        //         }
-        //     }
-        //     max.ok_or(anyhow!("no version found"))?
-        // };
-
-        // let path = self.path.join(format!("v{}", version));
-
-        let mut ron_map = ron::Map::new();
-
-        for dir_entry in fs::read_dir(&self.path)? {
-            let dir_entry = dir_entry?;
+        let (values, errors) = read_dir(&version_path(&self.path, None))?;
 
-            let filename = dir_entry.file_name();
+        for error in errors {
+            error!("{error}");
+        }
 
-            let filename = filename.to_str().ok_or(anyhow!("no filename"))?;
+        let dict = values
+            .into_iter()
+            .map(|(key, value)| (key, value.to_value()))
+            .collect::<Dict>();
 
-            let content = fs::read_to_string(dir_entry.path())?;
+        debug!("{:?}", dict);
 
-            debug!("{}", content);
+        Ok(Profile::Default.collect(dict))
+    }
+}
 
-            let value: ron::Value = ron::from_str(&content)?;
+/// A file of a config directory that could not be read
+#[derive(Debug, Clone)]
+pub struct FileError {
+    pub key: String,
+    pub path: PathBuf,
+    /// With the position of the error, for RON errors
+    pub message: String,
+}
 
-            debug!("{:?}", value);
+impl Display for FileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
 
-            ron_map.insert(ron::Value::String(filename.to_string()), value);
+/// Read every key of a config directory.
+/// A file that can't be read doesn't prevent the other keys from being loaded.
+pub fn read_dir(path: &Path) -> anyhow::Result<(BTreeMap<String, RonValue>, Vec<FileError>)> {
+    let mut values = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for dir_entry in fs::read_dir(path)? {
+        let dir_entry = dir_entry?;
+
+        let Some(key) = dir_entry.file_name().to_str().map(str::to_string) else {
+            warn!("{}: not a valid key", dir_entry.path().display());
+            continue;
+        };
+
+        // follow symlinks
+        let is_file = fs::metadata(dir_entry.path()).is_ok_and(|metadata| metadata.is_file());
+
+        // the other versions, and editor files
+        if !is_file || is_ignored(&key) {
+            debug!("ignore {}", dir_entry.path().display());
+            continue;
         }
 
-        debug!("{:?}", ron_map);
+        let value = fs::read_to_string(dir_entry.path())
+            .map_err(anyhow::Error::from)
+            .and_then(|content| {
+                debug!("{}", content);
+                super::ron_value::ron_from_str(&content)
+            });
+
+        match value {
+            Ok(value) => {
+                debug!("{:?}", value);
+                values.insert(key, value);
+            }
+            Err(e) => errors.push(FileError {
+                key,
+                path: dir_entry.path(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    Ok((values, errors))
+}
+
+/// Keys are field names, so they can't contain a `.`: `x.bak`, `.x.swp` or `x.tmp` are not keys.
+/// `x~` and `#x#` are backups of editors.
+fn is_ignored(name: &str) -> bool {
+    name.contains('.') || name.ends_with('~') || name.starts_with('#')
+}
+
+/// Synchronize the directory with the tree: write the keys that changed,
+/// and delete those that are not written anymore.
+/// Files that are not keys of the schema are left alone, unless the root is a map.
+/// The files of `file_errors` could not be parsed: the tree doesn't know what they contain,
+/// so they are never deleted.
+pub fn write(path: &Path, tree: &NodeContainer, file_errors: &[FileError]) -> anyhow::Result<()> {
+    let Node::Object(node_object) = &tree.node else {
+        return Ok(());
+    };
+
+    let unread = |key: &str| file_errors.iter().any(|error| error.key == key);
+
+    for (key, node) in &node_object.nodes {
+        let key_path = path.join(key);
+
+        if node.will_be_written() {
+            let content = ron::ser::to_string_pretty(node, ron::ser::PrettyConfig::new())?;
+
+            // don't wake up the watchers of the config for nothing
+            if fs::read_to_string(&key_path).ok().as_deref() != Some(content.as_str()) {
+                super::write_and_create_parent(&key_path, &content)?;
+            }
+        } else if unread(key) {
+            warn!("keep {}, it could not be read", key_path.display());
+        } else {
+            remove_key(&key_path)?;
+        }
+    }
+
+    // every key of a map is described by the schema
+    if node_object.template.is_some()
+        && let Ok(read_dir) = fs::read_dir(path)
+    {
+        for dir_entry in read_dir {
+            let dir_entry = dir_entry?;
+
+            if let Some(key) = dir_entry.file_name().to_str()
+                && !is_ignored(key)
+                && !node_object.nodes.contains_key(key)
+                && !unread(key)
+            {
+                remove_key(&dir_entry.path())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn remove_key(path: &Path) -> anyhow::Result<()> {
+    // not a directory
+    if fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir()) {
+        debug!("remove {}", path.display());
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+/// `v2` is the version 2
+pub fn parse_version(name: &str) -> Option<u64> {
+    name.strip_prefix('v')?.parse().ok()
+}
+
+/// The highest `v{N}` directory in `path`, like cosmic-config does
+pub fn latest_version(path: &Path) -> Option<u64> {
+    fs::read_dir(path)
+        .ok()?
+        .flatten()
+        .filter(|dir_entry| {
+            dir_entry
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_dir())
+        })
+        .filter_map(|dir_entry| parse_version(dir_entry.file_name().to_str()?))
+        .max()
+}
+
+/// The directory to read in `path`: the `version` asked when it exists,
+/// otherwise the version `path` points to, or the latest version found in it.
+/// `path` is returned as is when it contains no version.
+pub fn version_path(path: &Path, version: Option<u64>) -> PathBuf {
+    let (root, current) = split_version(path);
+
+    if let Some(version) = version
+        && root.join(format!("v{version}")).is_dir()
+    {
+        return root.join(format!("v{version}"));
+    }
+
+    match current.or_else(|| latest_version(root)) {
+        Some(version) => root.join(format!("v{version}")),
+        None => path.to_path_buf(),
+    }
+}
+
+/// The directory to write in `path`: the `version` of the schema, even if it doesn't exist yet,
+/// so the values of an older version are migrated to it.
+pub fn write_version_path(path: &Path, version: Option<u64>) -> PathBuf {
+    match version {
+        Some(version) => split_version(path).0.join(format!("v{version}")),
+        None => version_path(path, None),
+    }
+}
+
+fn split_version(path: &Path) -> (&Path, Option<u64>) {
+    match (version_of_path(path), path.parent()) {
+        (Some(version), Some(parent)) => (parent, Some(version)),
+        _ => (path, None),
+    }
+}
+
+/// `~/.config/cosmic/com.example.App/v1` is the version 1
+pub fn version_of_path(path: &Path) -> Option<u64> {
+    parse_version(path.file_name()?.to_str()?)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::Path};
+
+    use figment::{value::Value, Profile, Provider};
+
+    use super::{latest_version, read_dir, version_path, write_version_path, CosmicRonProvider};
+
+    #[test]
+    fn test_version_path() {
+        let path = Path::new("tests/cosmic_ron_versions");
+
+        let _ = fs::remove_dir_all(path);
+        fs::create_dir_all(path.join("v1")).unwrap();
+        fs::create_dir_all(path.join("v10")).unwrap();
+        fs::create_dir_all(path.join("other")).unwrap();
+        // a key, not a version
+        fs::write(path.join("v20"), "1").unwrap();
+
+        assert_eq!(latest_version(path), Some(10));
+        assert_eq!(version_path(path, None), path.join("v10"));
+        assert_eq!(version_path(path, Some(1)), path.join("v1"));
+        // not on disk yet
+        assert_eq!(version_path(path, Some(2)), path.join("v10"));
+        assert_eq!(version_path(&path.join("v1"), Some(2)), path.join("v1"));
+        assert_eq!(version_path(&path.join("v1"), Some(10)), path.join("v10"));
+        assert_eq!(version_path(&path.join("other"), None), path.join("other"));
+
+        assert_eq!(write_version_path(path, None), path.join("v10"));
+        assert_eq!(write_version_path(path, Some(2)), path.join("v2"));
+        assert_eq!(
+            write_version_path(&path.join("v1"), Some(2)),
+            path.join("v2")
+        );
+        assert_eq!(
+            write_version_path(&path.join("other"), None),
+            path.join("other")
+        );
+    }
+
+    #[test]
+    fn test_read_latest_version() {
+        let path = Path::new("tests/cosmic_ron_latest");
+
+        let _ = fs::remove_dir_all(path);
+        fs::create_dir_all(path.join("v1")).unwrap();
+        fs::create_dir_all(path.join("v2")).unwrap();
+        fs::write(path.join("v1/x"), "1").unwrap();
+        fs::write(path.join("v2/x"), "2").unwrap();
+
+        let dict = CosmicRonProvider::new(path)
+            .data()
+            .unwrap()
+            .remove(&Profile::Default)
+            .unwrap();
+
+        assert_eq!(dict["x"], Value::from(2u64));
+    }
+
+    #[test]
+    fn test_read_dir_errors() {
+        let path = Path::new("tests/cosmic_ron_errors");
+
+        let _ = fs::remove_dir_all(path);
+        fs::create_dir_all(path.join("v1")).unwrap();
+        fs::write(path.join("good"), "(x: 1)").unwrap();
+        fs::write(path.join("bad"), "(\n    x: 1,\n    y: ]\n)").unwrap();
+        fs::write(path.join("good~"), "(").unwrap();
+        fs::write(path.join(".good.swp"), "(").unwrap();
+        fs::write(path.join("good.bak"), "(").unwrap();
+
+        let (dict, errors) = read_dir(path).unwrap();
 
-        let data = Figment::new()
-            .merge(figment::providers::Serialized::from(
-                ron_map,
-                Profile::Default,
-            ))
-            .data()?;
+        assert_eq!(dict.keys().collect::<Vec<_>>(), ["good"]);
 
-        Ok(data)
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].key, "bad");
+        assert!(
+            errors[0].message.ends_with("at 3:8"),
+            "{}",
+            errors[0].message
+        );
     }
 }
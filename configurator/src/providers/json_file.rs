@@ -0,0 +1,60 @@
+//! Plain JSON, read with the parser of JSONC rather than the JSON provider of figment.
+//!
+//! The numbers are converted from their text, so the integers that don't fit in 64 bits
+//! are read exactly. serde_json reads them as floats.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use figment::{value::Value, Metadata, Profile, Provider};
+use serde::de::Error;
+
+pub struct JsonFileProvider {
+    path: PathBuf,
+}
+
+impl JsonFileProvider {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Provider for JsonFileProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("json provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        self.data_impl().map_err(figment::Error::custom)
+    }
+}
+
+impl JsonFileProvider {
+    fn data_impl(
+        &self,
+    ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
+        // like figment, a missing file is an empty config
+        let content = match fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Ok(figment::value::Map::new())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let spanned = super::jsonc::from_str(&content)
+            .map_err(|e| anyhow!("{}: {e}", self.path.display()))?;
+
+        match spanned.value {
+            Value::Dict(_, dict) => Ok(Profile::Default.collect(dict)),
+            _ => bail!("{}: the root is not an object", self.path.display()),
+        }
+    }
+}
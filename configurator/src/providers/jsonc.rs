@@ -0,0 +1,788 @@
+//! JSON with the extensions of hand written files: JSONC (VS Code settings) and JSON5.
+//!
+//! Comments, trailing commas, unquoted keys, single quoted strings, hexadecimal numbers,
+//! `Infinity` and `NaN` are accepted when reading.
+//! When writing, the original text is patched: only the values that changed are replaced,
+//! in plain JSON. Infinite and NaN numbers are written as `Infinity` and `NaN` in JSON5,
+//! and are an error in JSONC.
+
+use std::{collections::HashSet, fs, io, ops::Range, path::Path};
+
+use anyhow::{anyhow, bail};
+use figment::{
+    value::{Dict, Empty, Num, Tag, Value},
+    Metadata, Profile, Provider,
+};
+use serde::de::Error;
+
+use crate::{node::NodeContainer, utils::figment_value_to_f64};
+
+pub struct JsoncProvider {
+    path: std::path::PathBuf,
+}
+
+impl JsoncProvider {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Provider for JsoncProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("jsonc provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        self.data_impl().map_err(figment::Error::custom)
+    }
+}
+
+impl JsoncProvider {
+    fn data_impl(
+        &self,
+    ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
+        let content = fs::read_to_string(&self.path)?;
+
+        let spanned = from_str(&content).map_err(|e| anyhow!("{}: {e}", self.path.display()))?;
+
+        match spanned.value {
+            Value::Dict(_, dict) => Ok(Profile::Default.collect(dict)),
+            _ => bail!("{}: the root is not an object", self.path.display()),
+        }
+    }
+}
+
+/// A value, and where it is in the text
+pub struct Spanned {
+    pub value: Value,
+    pub span: Range<usize>,
+    pub kind: Kind,
+}
+
+pub enum Kind {
+    Object(Vec<Member>),
+    Array(Vec<Element>),
+    Scalar,
+}
+
+pub struct Member {
+    pub key: String,
+    /// Position of the key
+    pub start: usize,
+    pub value: Spanned,
+    /// Position after the comma that follows the value
+    pub comma_end: Option<usize>,
+}
+
+pub struct Element {
+    pub value: Spanned,
+    pub comma_end: Option<usize>,
+}
+
+impl Member {
+    fn end(&self) -> usize {
+        self.comma_end.unwrap_or(self.value.span.end)
+    }
+}
+
+pub fn from_str(str: &str) -> anyhow::Result<Spanned> {
+    let mut parser = Parser { str, pos: 0 };
+
+    // byte order mark
+    parser.consume("\u{feff}");
+    parser.skip_ws()?;
+
+    let value = parser.value()?;
+
+    parser.skip_ws()?;
+    if parser.pos != str.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+/// Update the document at `path` with the values of `tree`.
+/// Comments and formatting of the values that didn't change are kept.
+/// `json5` allows the numbers that only exist in JSON5, like `NaN`.
+pub fn to_string_edited(path: &Path, tree: &NodeContainer, json5: bool) -> anyhow::Result<String> {
+    let value = tree
+        .to_value(&Tag::Default)
+        .unwrap_or_else(|| Value::Dict(Tag::Default, Dict::new()));
+
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Ok(to_json(&value, "  ", "", json5)? + "\n");
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    // the values of a file we can't parse were not loaded, rewriting it would lose them
+    let spanned = from_str(&content).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+
+    let mut editor = Editor {
+        content: &content,
+        indent_unit: indent_unit(&content),
+        edits: Vec::new(),
+        json5,
+    };
+    editor.edit(&spanned, &value)?;
+
+    let mut edits = editor.edits;
+    let mut content = content.clone();
+
+    // from the end, so the positions of the other edits stay valid
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    for (range, text) in edits.into_iter().rev() {
+        content.replace_range(range, &text);
+    }
+
+    Ok(content)
+}
+
+struct Editor<'a> {
+    content: &'a str,
+    indent_unit: &'a str,
+    edits: Vec<(Range<usize>, String)>,
+    json5: bool,
+}
+
+impl Editor<'_> {
+    fn edit(&mut self, old: &Spanned, new: &Value) -> anyhow::Result<()> {
+        match (&old.kind, new) {
+            (Kind::Object(members), Value::Dict(_, dict)) if !members.is_empty() => {
+                self.edit_object(old, members, dict)?;
+            }
+            (Kind::Array(elements), Value::Array(_, values)) if elements.len() == values.len() => {
+                for (element, value) in elements.iter().zip(values) {
+                    self.edit(&element.value, value)?;
+                }
+            }
+            (Kind::Scalar, new) if scalar_eq(&old.value, new) => {}
+            (_, new) => {
+                let indent = line_indent(self.content, old.span.start);
+                let text = to_json(new, self.indent_unit, indent, self.json5)?;
+                self.edits.push((old.span.clone(), text));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn edit_object(
+        &mut self,
+        old: &Spanned,
+        members: &[Member],
+        dict: &Dict,
+    ) -> anyhow::Result<()> {
+        let mut kept = Vec::new();
+        let mut removed = Vec::new();
+        let mut written = HashSet::new();
+
+        for member in members {
+            match dict.get(&member.key) {
+                Some(value) if written.insert(member.key.as_str()) => {
+                    self.edit(&member.value, value)?;
+                    kept.push(member);
+                }
+                // removed, or a duplicated key
+                _ => removed.push(member),
+            }
+        }
+
+        for member in &removed {
+            let range = super::removal_range(self.content, member.start..member.end());
+            self.edits.push((range, String::new()));
+        }
+
+        let new_members = dict
+            .iter()
+            .filter(|(key, _)| !written.contains(key.as_str()))
+            .collect::<Vec<_>>();
+
+        let last = kept.last();
+        // the style of the document
+        let trailing_comma = members
+            .last()
+            .is_some_and(|member| member.comma_end.is_some());
+
+        if new_members.is_empty() {
+            // the comma of the new last member would be a trailing comma
+            if let Some(last) = last
+                && let Some(comma_end) = last.comma_end
+                && !trailing_comma
+            {
+                self.edits.push((comma_end - 1..comma_end, String::new()));
+            }
+            return Ok(());
+        }
+
+        if let Some(last) = last
+            && last.comma_end.is_none()
+        {
+            let end = last.value.span.end;
+            self.edits.push((end..end, ",".to_string()));
+        }
+
+        let close = old.span.end - 1;
+        let line_start = self.content[..close].rfind('\n').map_or(0, |pos| pos + 1);
+
+        // the closing brace is on its own line: one member per line
+        if self.content[line_start..close].trim().is_empty() {
+            let indent = match last {
+                Some(last) => line_indent(self.content, last.start).to_string(),
+                None => format!("{}{}", line_indent(self.content, close), self.indent_unit),
+            };
+
+            let mut text = String::new();
+
+            for (pos, (key, value)) in new_members.iter().enumerate() {
+                let value = to_json(value, self.indent_unit, &indent, self.json5)?;
+                let comma = if trailing_comma || pos + 1 < new_members.len() {
+                    ","
+                } else {
+                    ""
+                };
+                text.push_str(&format!(
+                    "{indent}{}: {value}{comma}\n",
+                    json::to_string(key)?
+                ));
+            }
+
+            self.edits.push((line_start..line_start, text));
+        } else {
+            let text = new_members
+                .iter()
+                .map(|(key, value)| {
+                    Ok(format!(
+                        "{}: {}",
+                        json::to_string(key)?,
+                        to_json(value, "", "", self.json5)?
+                    ))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+                .join(", ");
+
+            match last {
+                // after the comma
+                Some(last) => {
+                    let end = last.end();
+                    self.edits.push((end..end, format!(" {text}")));
+                }
+                None => {
+                    let start = old.span.start + 1;
+                    self.edits.push((start..start, text));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serialize to JSON. The lines after the first one are indented by `indent`.
+///
+/// Infinite and NaN numbers are written as the JSON5 literals, JSON has no way to write them.
+fn to_json(value: &Value, indent_unit: &str, indent: &str, json5: bool) -> anyhow::Result<String> {
+    let mut out = String::new();
+    write_json(&mut out, value, indent_unit, indent, json5)?;
+    Ok(out)
+}
+
+/// Same output as `serde_json`, compact when `indent_unit` is empty
+fn write_json(
+    out: &mut String,
+    value: &Value,
+    indent_unit: &str,
+    indent: &str,
+    json5: bool,
+) -> anyhow::Result<()> {
+    let (open, close, len) = match value {
+        Value::Dict(_, dict) => ('{', '}', dict.len()),
+        Value::Array(_, values) => ('[', ']', values.len()),
+        Value::Num(_, Num::F32(_) | Num::F64(_))
+            if let Some(num) = figment_value_to_f64(value)
+                && !num.is_finite() =>
+        {
+            if !json5 {
+                bail!("{num} can't be written in JSON");
+            }
+            out.push_str(if num.is_nan() {
+                "NaN"
+            } else if num > 0. {
+                "Infinity"
+            } else {
+                "-Infinity"
+            });
+            return Ok(());
+        }
+        value => {
+            out.push_str(&json::to_string(value)?);
+            return Ok(());
+        }
+    };
+
+    out.push(open);
+
+    if len == 0 {
+        out.push(close);
+        return Ok(());
+    }
+
+    let inner_indent = format!("{indent}{indent_unit}");
+
+    let write_element = |out: &mut String, pos: usize, key: Option<&str>, value: &Value| {
+        if pos > 0 {
+            out.push(',');
+        }
+        if !indent_unit.is_empty() {
+            out.push('\n');
+            out.push_str(&inner_indent);
+        }
+        if let Some(key) = key {
+            out.push_str(&json::to_string(key)?);
+            out.push_str(if indent_unit.is_empty() { ":" } else { ": " });
+        }
+        write_json(out, value, indent_unit, &inner_indent, json5)
+    };
+
+    match value {
+        Value::Dict(_, dict) => {
+            for (pos, (key, value)) in dict.iter().enumerate() {
+                write_element(out, pos, Some(key), value)?;
+            }
+        }
+        Value::Array(_, values) => {
+            for (pos, value) in values.iter().enumerate() {
+                write_element(out, pos, None, value)?;
+            }
+        }
+        _ => unreachable!(),
+    }
+
+    if !indent_unit.is_empty() {
+        out.push('\n');
+        out.push_str(indent);
+    }
+    out.push(close);
+
+    Ok(())
+}
+
+/// The indentation of the first indented line
+fn indent_unit(content: &str) -> &str {
+    content
+        .lines()
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ")
+}
+
+fn line_indent(content: &str, pos: usize) -> &str {
+    let line_start = content[..pos].rfind('\n').map_or(0, |pos| pos + 1);
+    let line = &content[line_start..];
+
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// `0x10` is equal to `16`, and `NaN` to itself
+fn scalar_eq(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::Num(_, a), Value::Num(_, b)) => match (a.to_i128(), b.to_i128()) {
+            (Some(a), Some(b)) => a == b,
+            _ => match (figment_value_to_f64(old), figment_value_to_f64(new)) {
+                (Some(a), Some(b)) => a == b || a.to_bits() == b.to_bits(),
+                _ => false,
+            },
+        },
+        (Value::String(_, a), Value::Char(_, b)) => a.chars().eq([*b]),
+        (old, new) => old == new,
+    }
+}
+
+struct Parser<'a> {
+    str: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.str[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn consume(&mut self, str: &str) -> bool {
+        if self.rest().starts_with(str) {
+            self.pos += str.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> anyhow::Result<()> {
+        if self.next() == Some(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn error(&self, msg: &str) -> anyhow::Error {
+        let before = &self.str[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |pos| pos + 1) + 1;
+
+        anyhow!("{msg} at {line}:{column}")
+    }
+
+    fn skip_ws(&mut self) -> anyhow::Result<()> {
+        loop {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start().len();
+
+            if self.consume("//") {
+                match self.rest().find('\n') {
+                    Some(end) => self.pos += end,
+                    None => self.pos = self.str.len(),
+                }
+            } else if self.consume("/*") {
+                match self.rest().find("*/") {
+                    Some(end) => self.pos += end + 2,
+                    None => bail!(self.error("unterminated block comment")),
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Spanned> {
+        let tag = Tag::Default;
+        let start = self.pos;
+
+        let (value, kind) = match self.peek() {
+            None => bail!(self.error("unexpected end of input")),
+            Some('{') => {
+                let members = self.object()?;
+
+                let dict = members
+                    .iter()
+                    .map(|member| (member.key.clone(), member.value.value.clone()))
+                    .collect();
+
+                (Value::Dict(tag, dict), Kind::Object(members))
+            }
+            Some('[') => {
+                let elements = self.array()?;
+
+                let values = elements
+                    .iter()
+                    .map(|element| element.value.value.clone())
+                    .collect();
+
+                (Value::Array(tag, values), Kind::Array(elements))
+            }
+            Some('"' | '\'') => (Value::String(tag, self.string()?), Kind::Scalar),
+            Some(c) if c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'I' | 'N') => {
+                (Value::Num(tag, self.number()?), Kind::Scalar)
+            }
+            Some(_) => {
+                let value = match self.identifier()?.to_string().as_str() {
+                    "true" => Value::Bool(tag, true),
+                    "false" => Value::Bool(tag, false),
+                    "null" => Value::Empty(tag, Empty::None),
+                    ident => bail!(self.error(&format!("unexpected identifier {ident}"))),
+                };
+
+                (value, Kind::Scalar)
+            }
+        };
+
+        Ok(Spanned {
+            value,
+            span: start..self.pos,
+            kind,
+        })
+    }
+
+    fn object(&mut self) -> anyhow::Result<Vec<Member>> {
+        let mut members = Vec::new();
+
+        self.expect('{')?;
+        self.skip_ws()?;
+
+        while !self.consume("}") {
+            let start = self.pos;
+
+            let key = match self.peek() {
+                Some('"' | '\'') => self.string()?,
+                _ => self.identifier()?.to_string(),
+            };
+
+            self.skip_ws()?;
+            self.expect(':')?;
+            self.skip_ws()?;
+            let value = self.value()?;
+            self.skip_ws()?;
+
+            let comma_end = self.consume(",").then_some(self.pos);
+            self.skip_ws()?;
+
+            members.push(Member {
+                key,
+                start,
+                value,
+                comma_end,
+            });
+
+            if comma_end.is_none() {
+                self.expect('}')?;
+                break;
+            }
+        }
+
+        Ok(members)
+    }
+
+    fn array(&mut self) -> anyhow::Result<Vec<Element>> {
+        let mut elements = Vec::new();
+
+        self.expect('[')?;
+        self.skip_ws()?;
+
+        while !self.consume("]") {
+            let value = self.value()?;
+            self.skip_ws()?;
+
+            let comma_end = self.consume(",").then_some(self.pos);
+            self.skip_ws()?;
+
+            elements.push(Element { value, comma_end });
+
+            if comma_end.is_none() {
+                self.expect(']')?;
+                break;
+            }
+        }
+
+        Ok(elements)
+    }
+
+    fn identifier(&mut self) -> anyhow::Result<&str> {
+        let start = self.pos;
+
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+            .unwrap_or(self.rest().len());
+
+        if len == 0 {
+            bail!(self.error("expected an identifier"));
+        }
+
+        self.pos += len;
+        Ok(&self.str[start..self.pos])
+    }
+
+    fn number(&mut self) -> anyhow::Result<Num> {
+        let negative = self.consume("-");
+        if !negative {
+            self.consume("+");
+        }
+
+        let sign = if negative { -1.0 } else { 1.0 };
+
+        if self.consume("Infinity") {
+            return Ok(Num::F64(sign * f64::INFINITY));
+        }
+        if self.consume("NaN") {
+            return Ok(Num::F64(f64::NAN));
+        }
+
+        let radix = if self.consume("0x") || self.consume("0X") {
+            16
+        } else {
+            10
+        };
+
+        let start = self.pos;
+        let mut prev = None;
+
+        while let Some(c) = self.peek() {
+            let is_exponent_sign =
+                radix == 10 && matches!(c, '+' | '-') && matches!(prev, Some('e' | 'E'));
+
+            if !(c.is_ascii_alphanumeric() || c == '.' || is_exponent_sign) {
+                break;
+            }
+
+            prev = self.next();
+        }
+
+        let digits = &self.str[start..self.pos];
+
+        if radix == 10 && digits.contains(['.', 'e', 'E']) {
+            let value = digits
+                .parse::<f64>()
+                .map_err(|e| self.error(&format!("invalid float: {e}")))?;
+
+            return Ok(Num::F64(sign * value));
+        }
+
+        let magnitude = u128::from_str_radix(digits, radix)
+            .map_err(|e| self.error(&format!("invalid integer: {e}")))?;
+
+        let num = if negative {
+            let value = 0i128
+                .checked_sub_unsigned(magnitude)
+                .ok_or_else(|| self.error("integer overflow"))?;
+
+            match i64::try_from(value) {
+                Ok(value) => Num::I64(value),
+                Err(_) => Num::I128(value),
+            }
+        } else {
+            match u64::try_from(magnitude) {
+                Ok(value) => Num::U64(value),
+                Err(_) => Num::U128(magnitude),
+            }
+        };
+
+        Ok(num)
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let mut string = String::new();
+
+        let quote = self.next().ok_or_else(|| self.error("expected a string"))?;
+
+        loop {
+            match self.next() {
+                Some(c) if c == quote => return Ok(string),
+                Some('\\') => {
+                    if let Some(c) = self.escape()? {
+                        string.push(c);
+                    }
+                }
+                Some('\n') | None => bail!(self.error("unterminated string")),
+                Some(c) => string.push(c),
+            }
+        }
+    }
+
+    /// `None` for an escaped line break, which continues the string on the next line
+    fn escape(&mut self) -> anyhow::Result<Option<char>> {
+        let c = match self.next() {
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('v') => '\u{b}',
+            Some('0') => '\0',
+            Some('\n') => return Ok(None),
+            Some('\r') => {
+                self.consume("\n");
+                return Ok(None);
+            }
+            Some('x') => {
+                let code = self.hex(2)?;
+                char::from_u32(code).ok_or_else(|| self.error("invalid escape"))?
+            }
+            Some('u') => {
+                let code = self.hex(4)?;
+
+                // surrogate pair
+                let code = if (0xD800..0xDC00).contains(&code) && self.consume("\\u") {
+                    let low = self.hex(4)?;
+                    0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF)
+                } else {
+                    code
+                };
+
+                char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?
+            }
+            Some(c) => c,
+            None => bail!(self.error("unterminated string")),
+        };
+
+        Ok(Some(c))
+    }
+
+    fn hex(&mut self, len: usize) -> anyhow::Result<u32> {
+        let digits = self
+            .rest()
+            .get(..len)
+            .ok_or_else(|| self.error("invalid escape"))?;
+
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid escape"))?;
+
+        self.pos += len;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use figment::value::{Empty, Num, Tag, Value};
+
+    use super::from_str;
+
+    #[test]
+    fn test_extensions() {
+        let spanned = from_str(
+            r#"// comment
+            {
+                /* block */ unquoted: 'single',
+                "hex": 0x10,
+                "float": .5,
+                "inf": -Infinity,
+                "line": "a\
+b",
+                "array": [1, null,],
+            }"#,
+        )
+        .unwrap();
+
+        let Value::Dict(_, dict) = spanned.value else {
+            panic!()
+        };
+
+        assert_eq!(dict["unquoted"], Value::from("single".to_string()));
+        assert_eq!(dict["hex"], Value::Num(Tag::Default, Num::U64(16)));
+        assert_eq!(dict["float"], Value::Num(Tag::Default, Num::F64(0.5)));
+        assert_eq!(
+            dict["inf"],
+            Value::Num(Tag::Default, Num::F64(f64::NEG_INFINITY))
+        );
+        assert_eq!(dict["line"], Value::from("ab".to_string()));
+        assert_eq!(
+            dict["array"],
+            Value::Array(
+                Tag::Default,
+                vec![
+                    Value::Num(Tag::Default, Num::U64(1)),
+                    Value::Empty(Tag::Default, Empty::None)
+                ]
+            )
+        );
+
+        assert!(from_str("{ a: 1 } 2").is_err());
+        assert!(from_str("{ a: 'b }").is_err());
+    }
+}
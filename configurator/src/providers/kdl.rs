@@ -0,0 +1,617 @@
+//! Mapping between KDL documents and figment values:
+//! - a document is a dict, with one entry per node name
+//! - `key 1` is a scalar, and `key null` is `None`
+//! - `key 1 2 3` is an array of scalars, and `key` alone is an empty array
+//! - `key { - 1; - { x 1; }; }` is any other array, with one `-` child per element
+//! - `key x=1 { y 2; }` is a dict, made of the properties and the children of the node.
+//!   `key {}` is an empty dict
+//! - a repeated name, like niri's `spawn-at-startup` or `window-rule`, is an array
+//!   with one element per node
+//! - nodes with arguments plus properties or children, like niri's `output "eDP-1" { … }`
+//!   or zellij's `bind "Ctrl g" { … }`, are keyed: all the nodes of this name are a dict
+//!   from their arguments to their properties and children
+//!
+//! The schema tells when a single node is the only element of an array.
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ::kdl::{KdlDocument, KdlEntry, KdlNode, KdlValue};
+use anyhow::{anyhow, bail};
+use figment::{
+    value::{Dict, Empty, Num, Tag, Value},
+    Metadata, Profile, Provider,
+};
+use serde::de::Error;
+
+use crate::{
+    node::{Node, NodeArrayTemplate, NodeContainer},
+    utils::f32_to_f64,
+};
+
+/// Name of the children representing array elements
+const ELEMENT: &str = "-";
+
+pub struct KdlProvider {
+    path: PathBuf,
+    /// Used to find the arrays written as a single node
+    tree: NodeContainer,
+}
+
+impl KdlProvider {
+    pub fn new(path: &Path, tree: &NodeContainer) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            tree: tree.clone(),
+        }
+    }
+}
+
+impl Provider for KdlProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("kdl provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        self.data_impl().map_err(figment::Error::custom)
+    }
+}
+
+impl KdlProvider {
+    fn data_impl(
+        &self,
+    ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
+        let content = fs::read_to_string(&self.path)?;
+
+        let document = content
+            .parse::<KdlDocument>()
+            .map_err(|e| anyhow!("{}: {e}", self.path.display()))?;
+
+        Ok(Profile::Default.collect(document_to_dict(&document, Some(&self.tree))?))
+    }
+}
+
+fn document_to_dict(
+    document: &KdlDocument,
+    schema: Option<&NodeContainer>,
+) -> anyhow::Result<Dict> {
+    let mut groups = BTreeMap::<&str, Vec<&KdlNode>>::new();
+
+    for node in document.nodes() {
+        groups.entry(node.name().value()).or_default().push(node);
+    }
+
+    let mut dict = Dict::new();
+
+    for (name, nodes) in groups {
+        let schema = child_schema(schema, name);
+
+        let value = match nodes.as_slice() {
+            nodes if is_keyed(nodes) => {
+                let mut entries = Dict::new();
+
+                for node in nodes {
+                    let key = node_key(node);
+                    let value = node_to_dict(node, child_schema(schema, &key))?;
+                    entries.insert(key, Value::Dict(Tag::Default, value));
+                }
+
+                Value::Dict(Tag::Default, entries)
+            }
+            [node] => node_to_value(node, schema)?,
+            nodes => Value::Array(
+                Tag::Default,
+                nodes
+                    .iter()
+                    .map(|node| node_to_value(node, element_schema(schema)))
+                    .collect::<anyhow::Result<_>>()?,
+            ),
+        };
+
+        dict.insert(name.to_string(), value);
+    }
+
+    Ok(dict)
+}
+
+fn node_to_value(node: &KdlNode, schema: Option<&NodeContainer>) -> anyhow::Result<Value> {
+    let element = element_schema(schema);
+
+    if let Some(element) = element
+        && !is_list(node, element)
+    {
+        return Ok(Value::Array(
+            Tag::Default,
+            vec![node_to_value(node, Some(element))?],
+        ));
+    }
+
+    let (arguments, properties): (Vec<_>, Vec<_>) = node
+        .entries()
+        .iter()
+        .partition(|entry| entry.name().is_none());
+
+    if properties.is_empty() && node.children().is_none() {
+        let value = match arguments.as_slice() {
+            [argument] if element.is_none() => from_kdl_value(argument.value()),
+            arguments => Value::Array(
+                Tag::Default,
+                arguments
+                    .iter()
+                    .map(|argument| from_kdl_value(argument.value()))
+                    .collect(),
+            ),
+        };
+
+        return Ok(value);
+    }
+
+    if !arguments.is_empty() {
+        bail!(
+            "node {} mixes arguments with properties or children",
+            node.name().value()
+        );
+    }
+
+    if properties.is_empty()
+        && let Some(children) = node.children()
+        && is_array(children)
+    {
+        let values = children
+            .nodes()
+            .iter()
+            .map(|child| node_to_value(child, element))
+            .collect::<anyhow::Result<_>>()?;
+
+        return Ok(Value::Array(Tag::Default, values));
+    }
+
+    Ok(Value::Dict(Tag::Default, node_to_dict(node, schema)?))
+}
+
+/// The properties and the children of a node
+fn node_to_dict(node: &KdlNode, schema: Option<&NodeContainer>) -> anyhow::Result<Dict> {
+    let mut dict = Dict::new();
+
+    for property in node.entries() {
+        if let Some(name) = property.name() {
+            dict.insert(name.value().to_string(), from_kdl_value(property.value()));
+        }
+    }
+
+    if let Some(children) = node.children() {
+        dict.extend(document_to_dict(children, schema)?);
+    }
+
+    Ok(dict)
+}
+
+/// Update the document at `path` with the values of `tree`.
+/// Comments and formatting of the nodes that already exist are kept.
+pub fn to_string_edited(path: &Path, tree: &NodeContainer) -> anyhow::Result<String> {
+    let (mut document, is_new) = match fs::read_to_string(path) {
+        Ok(content) => (content.parse::<KdlDocument>()?, false),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => (KdlDocument::new(), true),
+        Err(err) => return Err(err.into()),
+    };
+
+    let dict = match tree.to_value(&Tag::Default) {
+        Some(Value::Dict(_, dict)) => dict,
+        Some(value) => bail!("the root of a KDL document must be a dict: {value:?}"),
+        None => Dict::new(),
+    };
+
+    update_document(&mut document, &dict)?;
+
+    if is_new {
+        document.fmt();
+    }
+
+    Ok(document.to_string())
+}
+
+/// A dict is written as a document, other values as the `-` node of an array element
+pub fn figment_value_to_string(value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::Dict(_, dict) => {
+            let mut document = KdlDocument::new();
+            update_document(&mut document, dict)?;
+            document.fmt();
+            Ok(document.to_string())
+        }
+        value => Ok(new_node(ELEMENT, value)?.to_string()),
+    }
+}
+
+fn update_document(document: &mut KdlDocument, dict: &Dict) -> anyhow::Result<()> {
+    document
+        .nodes_mut()
+        .retain(|node| dict.contains_key(node.name().value()));
+
+    for (key, value) in dict {
+        let nodes = document.nodes_mut();
+
+        let positions = nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, node)| node.name().value() == key)
+            .map(|(pos, _)| pos)
+            .collect::<Vec<_>>();
+
+        match (positions.as_slice(), value) {
+            ([], value) => nodes.push(new_node(key, value)?),
+            (positions, Value::Dict(_, dict))
+                if is_keyed(&positions.iter().map(|pos| &nodes[*pos]).collect::<Vec<_>>()) =>
+            {
+                update_keyed_nodes(nodes, positions, key, dict)?;
+            }
+            (positions, Value::Array(_, values))
+                if positions.len() > 1 || !holds_array(&nodes[positions[0]], values) =>
+            {
+                update_repeated_nodes(nodes, positions, key, values)?;
+            }
+            ([first, duplicates @ ..], value) => {
+                update_node(&mut nodes[*first], value)?;
+                remove_nodes(nodes, duplicates);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// One node per entry of the dict, with the key as argument
+fn update_keyed_nodes(
+    nodes: &mut Vec<KdlNode>,
+    positions: &[usize],
+    name: &str,
+    dict: &Dict,
+) -> anyhow::Result<()> {
+    let mut updated = HashSet::new();
+    let mut removed = Vec::new();
+
+    for pos in positions {
+        let key = node_key(&nodes[*pos]);
+
+        // the last duplicate wins when reading, but the first one is updated
+        if updated.contains(&key) {
+            removed.push(*pos);
+            continue;
+        }
+
+        match dict.get(&key) {
+            Some(Value::Dict(_, value)) => {
+                update_node_dict(&mut nodes[*pos], value)?;
+                updated.insert(key);
+            }
+            Some(value) => bail!("{name} {key} must be a dict: {value:?}"),
+            None => removed.push(*pos),
+        }
+    }
+
+    let mut new = Vec::new();
+
+    for (key, value) in dict {
+        if updated.contains(key) {
+            continue;
+        }
+
+        let Value::Dict(_, value) = value else {
+            bail!("{name} {key} must be a dict: {value:?}")
+        };
+
+        let mut node = KdlNode::new(name);
+        node.entries_mut()
+            .push(KdlEntry::new(KdlValue::String(key.clone())));
+        update_node_dict(&mut node, value)?;
+        node.fmt();
+        new.push(node);
+    }
+
+    let last = positions[positions.len() - 1];
+    nodes.splice(last + 1..last + 1, new);
+    remove_nodes(nodes, &removed);
+
+    Ok(())
+}
+
+/// One node per element of the array
+fn update_repeated_nodes(
+    nodes: &mut Vec<KdlNode>,
+    positions: &[usize],
+    name: &str,
+    values: &[Value],
+) -> anyhow::Result<()> {
+    for (pos, value) in positions.iter().zip(values) {
+        update_node(&mut nodes[*pos], value)?;
+    }
+
+    let new = values
+        .iter()
+        .skip(positions.len())
+        .map(|value| {
+            let mut node = KdlNode::new(name);
+
+            match value {
+                // the schema tells that a single argument is an array here
+                Value::Array(_, values) if values.iter().all(is_scalar) => {
+                    update_arguments(&mut node, values)?
+                }
+                value => update_node(&mut node, value)?,
+            }
+
+            node.fmt();
+            Ok(node)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let last = positions[positions.len() - 1];
+    nodes.splice(last + 1..last + 1, new);
+    remove_nodes(nodes, positions.get(values.len()..).unwrap_or_default());
+
+    Ok(())
+}
+
+/// `positions` must be sorted
+fn remove_nodes(nodes: &mut Vec<KdlNode>, positions: &[usize]) {
+    for pos in positions.iter().rev() {
+        nodes.remove(*pos);
+    }
+}
+
+fn update_node(node: &mut KdlNode, value: &Value) -> anyhow::Result<()> {
+    match value {
+        Value::Dict(_, dict) => {
+            node.entries_mut().retain(|entry| entry.name().is_some());
+            update_node_dict(node, dict)?
+        }
+        // a single argument is read as a scalar when the schema doesn't tell otherwise
+        Value::Array(_, values)
+            if values.iter().all(is_scalar) && (values.len() != 1 || has_only_arguments(node)) =>
+        {
+            *node.children_mut() = None;
+            update_arguments(node, values)?;
+        }
+        Value::Array(_, values) => {
+            node.entries_mut().clear();
+
+            let children = node.ensure_children().nodes_mut();
+            children.retain(|node| node.name().value() == ELEMENT);
+            children.truncate(values.len());
+
+            for (pos, value) in values.iter().enumerate() {
+                match children.get_mut(pos) {
+                    Some(child) => update_node(child, value)?,
+                    None => children.push(new_node(ELEMENT, value)?),
+                }
+            }
+        }
+        value => {
+            *node.children_mut() = None;
+            update_arguments(node, std::slice::from_ref(value))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep the properties that are still scalars, the other entries become children.
+/// The arguments are kept: they are the key of a keyed node
+fn update_node_dict(node: &mut KdlNode, dict: &Dict) -> anyhow::Result<()> {
+    let mut properties = HashSet::new();
+
+    node.entries_mut().retain(|entry| {
+        entry.name().is_none_or(|name| {
+            dict.get(name.value()).is_some_and(is_scalar)
+                && properties.insert(name.value().to_string())
+        })
+    });
+
+    for entry in node.entries_mut() {
+        let Some(name) = entry.name() else {
+            continue;
+        };
+
+        let new = to_kdl_value(&dict[name.value()])?;
+
+        if !kdl_value_eq(entry.value(), &new) {
+            entry.set_value(new);
+        }
+    }
+
+    let children = dict
+        .iter()
+        .filter(|(key, _)| !properties.contains(*key))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect::<Dict>();
+
+    let has_arguments = node.entries().iter().any(|entry| entry.name().is_none());
+
+    if children.is_empty() && !properties.is_empty() {
+        *node.children_mut() = None;
+    } else if children.is_empty() && has_arguments && node.children().is_none() {
+        // a key without settings, like niri's `workspace "chat"`
+    } else {
+        update_document(node.ensure_children(), &children)?;
+    }
+
+    Ok(())
+}
+
+/// Replace the arguments, but keep the representation of those that are equal (e.g `0x10`)
+fn update_arguments(node: &mut KdlNode, values: &[Value]) -> anyhow::Result<()> {
+    let entries = node.entries_mut();
+
+    entries.retain(|entry| entry.name().is_none());
+    entries.truncate(values.len());
+
+    for (pos, value) in values.iter().enumerate() {
+        let new = to_kdl_value(value)?;
+
+        match entries.get_mut(pos) {
+            Some(entry) => {
+                if !kdl_value_eq(entry.value(), &new) {
+                    entry.set_value(new);
+                }
+            }
+            None => entries.push(KdlEntry::new(new)),
+        }
+    }
+
+    Ok(())
+}
+
+fn new_node(name: &str, value: &Value) -> anyhow::Result<KdlNode> {
+    let mut node = KdlNode::new(name);
+    update_node(&mut node, value)?;
+    node.fmt();
+    Ok(node)
+}
+
+fn to_kdl_value(value: &Value) -> anyhow::Result<KdlValue> {
+    let value = match value {
+        Value::String(_, str) => KdlValue::String(str.clone()),
+        Value::Char(_, char) => KdlValue::String(char.to_string()),
+        Value::Bool(_, bool) => KdlValue::Bool(*bool),
+        Value::Num(_, num) => match *num {
+            Num::F32(float) => KdlValue::Base10Float(f32_to_f64(float)),
+            Num::F64(float) => KdlValue::Base10Float(float),
+            num => KdlValue::Base10(
+                num.to_i128()
+                    .and_then(|int| i64::try_from(int).ok())
+                    .ok_or_else(|| anyhow!("{num:?} doesn't fit in a KDL integer"))?,
+            ),
+        },
+        Value::Empty(..) => KdlValue::Null,
+        Value::Dict(..) | Value::Array(..) => bail!("{value:?} is not a KDL value"),
+    };
+
+    Ok(value)
+}
+
+fn from_kdl_value(value: &KdlValue) -> Value {
+    match value {
+        KdlValue::String(str) | KdlValue::RawString(str) => Value::from(str.clone()),
+        KdlValue::Base2(int)
+        | KdlValue::Base8(int)
+        | KdlValue::Base10(int)
+        | KdlValue::Base16(int) => Value::from(*int),
+        KdlValue::Base10Float(float) => Value::from(*float),
+        KdlValue::Bool(bool) => Value::from(*bool),
+        KdlValue::Null => Value::Empty(Tag::Default, Empty::None),
+    }
+}
+
+/// Integers in another base are equal to their decimal value
+fn kdl_value_eq(a: &KdlValue, b: &KdlValue) -> bool {
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        return a == b;
+    }
+
+    if let (Some(a), Some(b)) = (a.as_string(), b.as_string()) {
+        return a == b;
+    }
+
+    a == b
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Dict(..) | Value::Array(..))
+}
+
+fn is_array(children: &KdlDocument) -> bool {
+    !children.nodes().is_empty()
+        && children
+            .nodes()
+            .iter()
+            .all(|node| node.name().value() == ELEMENT)
+}
+
+/// Whether the nodes of a name are keyed by their arguments
+fn is_keyed(nodes: &[&KdlNode]) -> bool {
+    let has_arguments = |node: &&KdlNode| node.entries().iter().any(|entry| entry.name().is_none());
+
+    nodes.iter().all(has_arguments)
+        && nodes.iter().any(|node| {
+            node.children().is_some() || node.entries().iter().any(|entry| entry.name().is_some())
+        })
+}
+
+/// The string argument of a keyed node, or all its arguments as written in KDL
+fn node_key(node: &KdlNode) -> String {
+    let arguments = node
+        .entries()
+        .iter()
+        .filter(|entry| entry.name().is_none())
+        .collect::<Vec<_>>();
+
+    match arguments.as_slice() {
+        [argument] if let Some(str) = argument.value().as_string() => str.to_string(),
+        arguments => arguments
+            .iter()
+            .map(|argument| argument.value().to_string())
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+fn has_only_arguments(node: &KdlNode) -> bool {
+    node.children().is_none()
+        && !node.entries().is_empty()
+        && node.entries().iter().all(|entry| entry.name().is_none())
+}
+
+/// Whether `node` is a whole list of `element`, rather than one of its elements
+fn is_list(node: &KdlNode, element: &NodeContainer) -> bool {
+    match node.children() {
+        None => {
+            node.entries().iter().all(|entry| entry.name().is_none())
+                && (node.entries().is_empty() || element_schema(Some(element)).is_none())
+        }
+        Some(children) => node.entries().is_empty() && is_array(children),
+    }
+}
+
+/// Whether a single node can hold the whole array, rather than only its first element
+fn holds_array(node: &KdlNode, values: &[Value]) -> bool {
+    match node.children() {
+        None => {
+            node.entries().iter().all(|entry| entry.name().is_none())
+                && values.iter().all(is_scalar)
+        }
+        Some(children) => node.entries().is_empty() && is_array(children),
+    }
+}
+
+/// The schema of the values, through options
+fn schema_node(schema: &NodeContainer) -> &Node {
+    match &schema.node {
+        Node::Option(node_option) => schema_node(&node_option.inner),
+        node => node,
+    }
+}
+
+fn child_schema<'a>(schema: Option<&'a NodeContainer>, name: &str) -> Option<&'a NodeContainer> {
+    match schema_node(schema?) {
+        Node::Object(node_object) => node_object
+            .nodes
+            .get(name)
+            .or(node_object.template.as_deref()),
+        _ => None,
+    }
+}
+
+fn element_schema(schema: Option<&NodeContainer>) -> Option<&NodeContainer> {
+    match schema_node(schema?) {
+        Node::Array(node_array) => match &node_array.template {
+            NodeArrayTemplate::All(template) => Some(template),
+            NodeArrayTemplate::FirstN(_) => None,
+        },
+        _ => None,
+    }
+}
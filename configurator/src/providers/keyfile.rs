@@ -0,0 +1,547 @@
+//! Keyfiles, as used by `.desktop` files, KDE's kconfig or GTK's `settings.ini`.
+//!
+//! Groups (`[Group]`) are objects, and keys written before the first group belong to the root.
+//! Values are plain text, typed with the schema by [`typed_value`]:
+//! - `true`/`false` for booleans
+//! - `a;b;c;` for arrays, with `\;` escaping a `;` inside an element
+//! - `\s`, `\n`, `\t`, `\r` and `\\` escapes for strings
+//!
+//! Localized keys like `Name[fr]` are typed like `Name` when the schema doesn't define them,
+//! and are settings of their own in a tree with [`NodeContainer::enable_localized_keys`].
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use figment::{
+    value::{Dict, Num, Tag, Value},
+    Metadata, Profile, Provider,
+};
+use serde::de::Error;
+
+use crate::node::{without_locale, Node, NodeContainer, NodeObject};
+
+pub struct KeyfileProvider {
+    path: PathBuf,
+    /// Used to type the values
+    tree: NodeContainer,
+}
+
+impl KeyfileProvider {
+    pub fn new(path: &Path, tree: &NodeContainer) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            tree: tree.clone(),
+        }
+    }
+}
+
+impl Provider for KeyfileProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("keyfile provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        self.data_impl().map_err(figment::Error::custom)
+    }
+}
+
+impl KeyfileProvider {
+    fn data_impl(
+        &self,
+    ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
+        let content = fs::read_to_string(&self.path)?;
+
+        let mut dict = Dict::new();
+
+        for section in parse(&content) {
+            let entries = match section.name {
+                Some(name) => match dict
+                    .entry(name)
+                    .or_insert_with(|| Value::Dict(Tag::Default, Dict::new()))
+                {
+                    Value::Dict(_, entries) => entries,
+                    _ => bail!("{}: a group has the name of a key", self.path.display()),
+                },
+                None => &mut dict,
+            };
+
+            for line in section.lines {
+                if let Line::Entry { key, value, .. } = line {
+                    entries.insert(key, Value::String(Tag::Default, value));
+                }
+            }
+        }
+
+        match typed_value(Value::Dict(Tag::Default, dict), &self.tree) {
+            Value::Dict(_, dict) => Ok(Profile::Default.collect(dict)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Convert the text of the values to the types of the schema.
+/// Values that don't fit their node are kept as text.
+pub fn typed_value(value: Value, node: &NodeContainer) -> Value {
+    match (value, &node.node) {
+        (Value::Dict(tag, dict), Node::Object(node_object)) => Value::Dict(
+            tag,
+            dict.into_iter()
+                .map(|(key, value)| {
+                    let value = match child(node_object, &key) {
+                        Some(node) => typed_value(value, node),
+                        None => value,
+                    };
+                    (key, value)
+                })
+                .collect(),
+        ),
+        (Value::Dict(tag, dict), Node::Option(node_option)) => {
+            typed_value(Value::Dict(tag, dict), &node_option.inner)
+        }
+        (Value::String(tag, raw), _) => {
+            typed_str(tag, &raw, node).unwrap_or(Value::String(tag, raw))
+        }
+        (value, _) => value,
+    }
+}
+
+fn typed_str(tag: Tag, raw: &str, node: &NodeContainer) -> Option<Value> {
+    let value = match &node.node {
+        Node::Bool(_) => Value::Bool(tag, parse_bool(raw)?),
+        Node::Number(node_number) => {
+            Value::Num(tag, node_number.try_parse_plain(raw).ok()?.into_num())
+        }
+        Node::Option(node_option) => return typed_str(tag, raw, &node_option.inner),
+        Node::Array(node_array) => Value::Array(
+            tag,
+            split_list(raw)
+                .into_iter()
+                .enumerate()
+                .map(|(pos, item)| {
+                    typed_str(tag, item, &node_array.template(Some(pos)))
+                        .unwrap_or_else(|| Value::String(tag, unescape(item)))
+                })
+                .collect(),
+        ),
+        Node::String(_) | Node::Char(_) | Node::Enum(_) | Node::Any(_) => {
+            Value::String(tag, unescape(raw))
+        }
+        _ => return None,
+    };
+
+    Some(value)
+}
+
+/// The node of `key`. Localized keys fallback to the node of the key without the locale
+fn child<'a>(node_object: &'a NodeObject, key: &str) -> Option<&'a NodeContainer> {
+    node_object
+        .nodes
+        .get(key)
+        .or_else(|| node_object.nodes.get(without_locale(key)?))
+        .or(node_object.template.as_deref())
+}
+
+/// The object of a group, to know which keys are managed by the schema
+fn object_of(node: &NodeContainer) -> Option<&NodeObject> {
+    match &node.node {
+        Node::Object(node_object) => Some(node_object),
+        Node::Option(node_option) => object_of(&node_option.inner),
+        _ => None,
+    }
+}
+
+/// Update the keyfile at `path` with the values of `tree`.
+/// Comments, and the groups and keys unknown to the schema are kept.
+pub fn to_string_edited(path: &Path, tree: &NodeContainer) -> anyhow::Result<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    let dict = match tree.to_value(&Tag::Default) {
+        Some(Value::Dict(_, dict)) => dict,
+        Some(value) => bail!("the root of a keyfile must be an object: {value:?}"),
+        None => Dict::new(),
+    };
+
+    let mut sections = parse(&content);
+    update(&mut sections, &dict, object_of(tree))?;
+
+    Ok(to_string(&sections))
+}
+
+/// An object is written as a keyfile, other values as the value of a key
+pub fn figment_value_to_string(value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::Dict(_, dict) => {
+            let mut sections = parse("");
+            update(&mut sections, dict, None)?;
+            Ok(to_string(&sections))
+        }
+        value => to_raw(value, false),
+    }
+}
+
+struct Section {
+    /// `None` for the keys before the first group
+    name: Option<String>,
+    header: String,
+    lines: Vec<Line>,
+}
+
+enum Line {
+    /// Comments and blank lines
+    Other(String),
+    Entry {
+        key: String,
+        /// Everything before the value, like `key = `
+        prefix: String,
+        value: String,
+    },
+}
+
+fn parse(content: &str) -> Vec<Section> {
+    let mut sections = vec![Section {
+        name: None,
+        header: String::new(),
+        lines: Vec::new(),
+    }];
+
+    for line in content.lines() {
+        let trimmed = line.trim();
+
+        if let Some(name) = trimmed
+            .strip_prefix('[')
+            .and_then(|name| name.strip_suffix(']'))
+        {
+            sections.push(Section {
+                name: Some(name.to_string()),
+                header: line.to_string(),
+                lines: Vec::new(),
+            });
+            continue;
+        }
+
+        let line = match line.split_once('=') {
+            Some((key, value)) if !trimmed.starts_with(['#', ';']) && !key.trim().is_empty() => {
+                let value = value.trim_start();
+                let prefix = &line[..line.len() - value.len()];
+
+                Line::Entry {
+                    key: key.trim().to_string(),
+                    prefix: prefix.to_string(),
+                    value: value.trim_end().to_string(),
+                }
+            }
+            _ => Line::Other(line.to_string()),
+        };
+
+        sections.last_mut().unwrap().lines.push(line);
+    }
+
+    sections
+}
+
+fn to_string(sections: &[Section]) -> String {
+    let mut content = String::new();
+
+    for section in sections {
+        if section.name.is_some() {
+            content.push_str(&section.header);
+            content.push('\n');
+        }
+
+        for line in &section.lines {
+            match line {
+                Line::Other(line) => content.push_str(line),
+                Line::Entry { prefix, value, .. } => {
+                    content.push_str(prefix);
+                    content.push_str(value);
+                }
+            }
+            content.push('\n');
+        }
+    }
+
+    content
+}
+
+fn update(
+    sections: &mut Vec<Section>,
+    dict: &Dict,
+    node_object: Option<&NodeObject>,
+) -> anyhow::Result<()> {
+    let (groups, entries): (Dict, Dict) = dict
+        .iter()
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .partition(|(_, value)| matches!(value, Value::Dict(..)));
+
+    update_entries(&mut sections[0].lines, &entries, node_object)?;
+
+    sections.retain(|section| match &section.name {
+        Some(name) => groups.contains_key(name) || !is_managed(node_object, name),
+        None => true,
+    });
+
+    for (name, group) in &groups {
+        let Value::Dict(_, group) = group else {
+            unreachable!()
+        };
+
+        let group_object = node_object
+            .and_then(|node_object| child(node_object, name))
+            .and_then(object_of);
+
+        match sections
+            .iter_mut()
+            .find(|section| section.name.as_ref() == Some(name))
+        {
+            Some(section) => update_entries(&mut section.lines, group, group_object)?,
+            None => {
+                let mut section = Section {
+                    name: Some(name.clone()),
+                    header: format!("[{name}]"),
+                    lines: Vec::new(),
+                };
+                update_entries(&mut section.lines, group, group_object)?;
+
+                // separate the groups with a blank line
+                let last = sections.last_mut().unwrap();
+                if (last.name.is_some() || !last.lines.is_empty())
+                    && !matches!(last.lines.last(), Some(Line::Other(line)) if line.trim().is_empty())
+                {
+                    last.lines.push(Line::Other(String::new()));
+                }
+
+                sections.push(section);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Update the keys of a group, keeping the representation of the values that didn't change
+fn update_entries(
+    lines: &mut Vec<Line>,
+    values: &Dict,
+    node_object: Option<&NodeObject>,
+) -> anyhow::Result<()> {
+    let mut seen = HashSet::new();
+
+    lines.retain(|line| match line {
+        Line::Entry { key, .. } => match values.get(key) {
+            Some(value) => is_written(value) && seen.insert(key.clone()),
+            None => !is_managed(node_object, key),
+        },
+        Line::Other(_) => true,
+    });
+
+    for line in lines.iter_mut() {
+        let Line::Entry {
+            key, value: raw, ..
+        } = line
+        else {
+            continue;
+        };
+
+        // keys unknown to the schema
+        let Some(value) = values.get(key) else {
+            continue;
+        };
+
+        let unchanged = match node_object.and_then(|node_object| child(node_object, key)) {
+            Some(node) => typed_value(Value::String(Tag::Default, raw.clone()), node) == *value,
+            None => *raw == to_raw(value, false)?,
+        };
+
+        if !unchanged {
+            *raw = to_raw(value, false)?;
+        }
+    }
+
+    let mut pos = lines
+        .iter()
+        .rposition(|line| matches!(line, Line::Entry { .. }))
+        .map_or(lines.len(), |pos| pos + 1);
+
+    for (key, value) in values {
+        if !is_written(value) || seen.contains(key) {
+            continue;
+        }
+
+        lines.insert(
+            pos,
+            Line::Entry {
+                key: key.clone(),
+                prefix: format!("{key}="),
+                value: to_raw(value, false)?,
+            },
+        );
+        pos += 1;
+    }
+
+    Ok(())
+}
+
+/// Keys that are not managed by the schema are never removed
+fn is_managed(node_object: Option<&NodeObject>, key: &str) -> bool {
+    node_object.is_some_and(|node_object| {
+        node_object.nodes.contains_key(key)
+            || node_object.template.is_some()
+            || without_locale(key).is_some_and(|key| node_object.nodes.contains_key(key))
+    })
+}
+
+/// A keyfile has no null: `None` is written by omitting the key
+fn is_written(value: &Value) -> bool {
+    !matches!(value, Value::Empty(..))
+}
+
+fn to_raw(value: &Value, in_list: bool) -> anyhow::Result<String> {
+    let raw = match value {
+        Value::String(_, str) => escape(str, in_list),
+        Value::Char(_, char) => escape(&char.to_string(), in_list),
+        Value::Bool(_, bool) => bool.to_string(),
+        Value::Num(_, num) => num_to_string(num),
+        Value::Array(_, values) if !in_list => values
+            .iter()
+            .map(|value| to_raw(value, true).map(|raw| raw + ";"))
+            .collect::<anyhow::Result<String>>()?,
+        value => bail!("{value:?} can't be written in a keyfile"),
+    };
+
+    Ok(raw)
+}
+
+fn num_to_string(num: &Num) -> String {
+    match *num {
+        Num::U8(v) => v.to_string(),
+        Num::U16(v) => v.to_string(),
+        Num::U32(v) => v.to_string(),
+        Num::U64(v) => v.to_string(),
+        Num::U128(v) => v.to_string(),
+        Num::USize(v) => v.to_string(),
+        Num::I8(v) => v.to_string(),
+        Num::I16(v) => v.to_string(),
+        Num::I32(v) => v.to_string(),
+        Num::I64(v) => v.to_string(),
+        Num::I128(v) => v.to_string(),
+        Num::ISize(v) => v.to_string(),
+        Num::F32(v) => v.to_string(),
+        Num::F64(v) => v.to_string(),
+    }
+}
+
+fn parse_bool(raw: &str) -> Option<bool> {
+    match raw {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+/// Split on the `;` that are not escaped. The last `;` is optional
+fn split_list(raw: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (pos, char) in raw.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ';' => {
+                items.push(&raw[start..pos]);
+                start = pos + 1;
+            }
+            _ => {}
+        }
+    }
+
+    if start < raw.len() {
+        items.push(&raw[start..]);
+    }
+
+    items
+}
+
+fn escape(str: &str, in_list: bool) -> String {
+    let mut escaped = String::with_capacity(str.len());
+
+    for (pos, char) in str.chars().enumerate() {
+        match char {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            // leading spaces would be trimmed
+            ' ' if pos == 0 => escaped.push_str("\\s"),
+            ';' if in_list => escaped.push_str("\\;"),
+            char => escaped.push(char),
+        }
+    }
+
+    escaped
+}
+
+fn unescape(raw: &str) -> String {
+    let mut str = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(char) = chars.next() {
+        if char != '\\' {
+            str.push(char);
+            continue;
+        }
+
+        match chars.next() {
+            Some('s') => str.push(' '),
+            Some('n') => str.push('\n'),
+            Some('t') => str.push('\t'),
+            Some('r') => str.push('\r'),
+            Some(char) => str.push(char),
+            None => str.push('\\'),
+        }
+    }
+
+    str
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_split_list() {
+        assert_eq!(split_list(""), Vec::<&str>::new());
+        assert_eq!(split_list("a;b;"), vec!["a", "b"]);
+        assert_eq!(split_list("a;b"), vec!["a", "b"]);
+        assert_eq!(split_list(r"a\;b;c;"), vec![r"a\;b", "c"]);
+        assert_eq!(split_list(r"a\\;b"), vec![r"a\\", "b"]);
+    }
+
+    #[test]
+    fn test_escape() {
+        for str in [" a", "a\nb", r"a\b", "a;b", "\t"] {
+            assert_eq!(unescape(&escape(str, true)), str);
+            assert_eq!(unescape(&escape(str, false)), str);
+        }
+
+        assert_eq!(escape(" a b", false), r"\sa b");
+    }
+
+    #[test]
+    fn test_without_locale() {
+        assert_eq!(without_locale("Name[fr]"), Some("Name"));
+        assert_eq!(without_locale("Name[sr@latin]"), Some("Name"));
+        assert_eq!(without_locale("Name"), None);
+    }
+}
@@ -1,17 +1,32 @@
 use anyhow::anyhow;
-use std::{fs, path::Path};
+use std::{
+    fs::{self, File},
+    io::Write,
+    ops::Range,
+    path::Path,
+};
 
 use configurator_utils::ConfigFormat;
-pub use cosmic_ron::CosmicRonProvider;
+pub use cosmic_ron::{CosmicRonProvider, FileError};
 use figment::{
     providers::{self, Format},
     value::Value,
-    Figment, Profile, Provider,
+    Figment, Metadata, Profile, Provider,
 };
 
-mod cosmic_ron;
+use crate::node::NodeContainer;
+use ron_value::RonValue;
+
+pub mod cosmic_ron;
+mod json_file;
+pub mod jsonc;
+pub mod kdl;
+pub mod keyfile;
+mod ron_file;
+pub mod ron_value;
 #[cfg(test)]
 mod tests;
+pub mod toml;
 
 pub struct BoxedProvider(Box<dyn Provider>);
 
@@ -31,49 +46,210 @@ impl Provider for BoxedProvider {
     }
 }
 
+/// `tree` is used by the formats whose values are not typed, like keyfiles
 #[instrument(skip_all)]
-pub fn read_from_format<P: AsRef<Path>>(path: P, format: &ConfigFormat) -> BoxedProvider {
+pub fn read_from_format<P: AsRef<Path>>(
+    path: P,
+    format: &ConfigFormat,
+    tree: &NodeContainer,
+) -> BoxedProvider {
     debug!("{:?}:{}", path.as_ref(), format);
 
     match format {
-        ConfigFormat::Json => BoxedProvider(Box::new(providers::Json::file(path))),
+        ConfigFormat::Json => {
+            BoxedProvider(Box::new(json_file::JsonFileProvider::new(path.as_ref())))
+        }
         ConfigFormat::CosmicRon => BoxedProvider(Box::new(
             crate::providers::CosmicRonProvider::new(path.as_ref()),
         )),
+        ConfigFormat::Toml => BoxedProvider(Box::new(providers::Toml::file(path))),
+        ConfigFormat::Yaml => BoxedProvider(Box::new(providers::Yaml::file(path))),
+        ConfigFormat::Kdl => BoxedProvider(Box::new(kdl::KdlProvider::new(path.as_ref(), tree))),
+        ConfigFormat::Keyfile => {
+            BoxedProvider(Box::new(keyfile::KeyfileProvider::new(path.as_ref(), tree)))
+        }
+        ConfigFormat::Ron => BoxedProvider(Box::new(ron_file::RonFileProvider::new(path.as_ref()))),
+        ConfigFormat::Jsonc | ConfigFormat::Json5 => {
+            BoxedProvider(Box::new(jsonc::JsoncProvider::new(path.as_ref())))
+        }
     }
 }
 
-pub fn write<P: AsRef<Path>>(path: P, format: &ConfigFormat, data: &Value) -> anyhow::Result<()> {
-    // dbg!(&data);
+/// Everything a page reads from a config, parsed once
+pub struct ConfigRead {
+    pub provider: BoxedProvider,
+    /// The values as they were written, for the RON formats.
+    /// See [`crate::node::NodeObject::unknown_ron`]
+    pub ron: Option<RonValue>,
+    /// The files of a config directory that could not be parsed
+    pub file_errors: Vec<FileError>,
+}
+
+pub fn read_config<P: AsRef<Path>>(
+    path: P,
+    format: &ConfigFormat,
+    tree: &NodeContainer,
+) -> ConfigRead {
+    let res = match format {
+        ConfigFormat::CosmicRon => {
+            cosmic_ron::read_dir(&cosmic_ron::version_path(path.as_ref(), None)).map(
+                |(values, file_errors)| {
+                    for error in &file_errors {
+                        error!("{error}");
+                    }
+                    (RonValue::Struct(values.into_iter().collect()), file_errors)
+                },
+            )
+        }
+        ConfigFormat::Ron => ron_file::read_ron(path.as_ref()).map(|ron| (ron, Vec::new())),
+        _ => Err(anyhow!("not a RON format")),
+    };
+
+    match res {
+        Ok((ron, file_errors)) => ConfigRead {
+            provider: BoxedProvider(Box::new(RonValueProvider(ron.to_value()))),
+            ron: Some(ron),
+            file_errors,
+        },
+        // the provider of the format reports the error
+        Err(_) => ConfigRead {
+            provider: read_from_format(path, format, tree),
+            ron: None,
+            file_errors: Vec::new(),
+        },
+    }
+}
+
+/// The values of a RON config that was already read
+struct RonValueProvider(Value);
+
+impl Provider for RonValueProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("ron provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        match &self.0 {
+            Value::Dict(_, dict) => Ok(Profile::Default.collect(dict.clone())),
+            _ => Err(figment::Error::from("the root is not a struct".to_string())),
+        }
+    }
+}
+
+/// `file_errors` are the files of the config that could not be parsed, they are never deleted
+pub fn write<P: AsRef<Path>>(
+    path: P,
+    format: &ConfigFormat,
+    tree: &NodeContainer,
+    file_errors: &[FileError],
+) -> anyhow::Result<()> {
     match format {
         ConfigFormat::Json => {
-            let content = json::to_string_pretty(&data)?;
+            let content = json::to_string_pretty(tree)?;
             write_and_create_parent(path, &content)?;
         }
-        ConfigFormat::CosmicRon => {
-            if let Some(dict) = data.as_dict() {
-                for (key, value) in dict {
-                    let content =
-                        ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new()).unwrap();
-                    write_and_create_parent(path.as_ref().join(key), &content)?;
-                }
-            }
+        ConfigFormat::CosmicRon => cosmic_ron::write(path.as_ref(), tree, file_errors)?,
+        ConfigFormat::Toml => {
+            let content = toml::to_string_edited(path.as_ref(), tree)?;
+            write_and_create_parent(path, &content)?;
+        }
+        ConfigFormat::Yaml => {
+            // YAML would write enum variants as tags (`!Variant`), which can't be read back
+            let content = serde_yaml::to_string(&tree.serialize_variants_as_maps())?;
+            write_and_create_parent(path, &content)?;
+        }
+        ConfigFormat::Kdl => {
+            let content = kdl::to_string_edited(path.as_ref(), tree)?;
+            write_and_create_parent(path, &content)?;
+        }
+        ConfigFormat::Keyfile => {
+            let content = keyfile::to_string_edited(path.as_ref(), tree)?;
+            write_and_create_parent(path, &content)?;
+        }
+        ConfigFormat::Ron => {
+            let content = ron_file::to_string_edited(path.as_ref(), tree)?;
+            write_and_create_parent(path, &content)?;
+        }
+        ConfigFormat::Jsonc | ConfigFormat::Json5 => {
+            let content =
+                jsonc::to_string_edited(path.as_ref(), tree, *format == ConfigFormat::Json5)?;
+            write_and_create_parent(path, &content)?;
         }
     }
 
     Ok(())
 }
 
-fn write_and_create_parent<P: AsRef<Path>, C: AsRef<[u8]>>(
+/// Range to remove an entry of a document edited in place:
+/// the whole lines of the entry when it is alone on them
+fn removal_range(content: &str, entry: Range<usize>) -> Range<usize> {
+    let line_start = content[..entry.start].rfind('\n').map_or(0, |pos| pos + 1);
+    let line_end = content[entry.end..]
+        .find('\n')
+        .map(|pos| entry.end + pos + 1);
+
+    match line_end {
+        Some(line_end)
+            if content[line_start..entry.start].trim().is_empty()
+                && content[entry.end..line_end].trim().is_empty() =>
+        {
+            line_start..line_end
+        }
+        _ => entry,
+    }
+}
+
+/// Write atomically, so a crash or a full disk never leaves a truncated config
+/// that the app could read: the content is written to a temporary file,
+/// synced, then renamed over the config.
+pub fn write_and_create_parent<P: AsRef<Path>, C: AsRef<[u8]>>(
     path: P,
     contents: C,
 ) -> anyhow::Result<()> {
-    if !path.as_ref().exists() {
-        let parent = path.as_ref().parent().ok_or(anyhow!("no parent"))?;
-        fs::create_dir_all(parent)?;
+    let mut path = path.as_ref().to_path_buf();
+
+    // replace the target of a symlink, not the symlink itself
+    if fs::symlink_metadata(&path).is_ok_and(|metadata| metadata.is_symlink()) {
+        path = fs::canonicalize(&path)?;
+    }
+
+    let parent = path.parent().ok_or(anyhow!("no parent"))?;
+    let file_name = path.file_name().ok_or(anyhow!("no file name"))?;
+
+    fs::create_dir_all(parent)?;
+
+    // hidden, and not a key of a cosmic config
+    let tmp_path = parent.join(format!(".{}.tmp", file_name.to_string_lossy()));
+
+    let res = (|| {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents.as_ref())?;
+
+        if let Ok(metadata) = fs::metadata(&path) {
+            file.set_permissions(metadata.permissions())?;
+        }
+
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)
+    })();
+
+    if let Err(err) = res {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(anyhow!("{}: {err}", path.display()));
     }
 
-    fs::write(path, contents)?;
+    // the rename is only durable once the directory is synced
+    let parent = if parent.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        parent
+    };
+
+    if let Err(err) = File::open(parent).and_then(|dir| dir.sync_all()) {
+        warn!("can't sync {}: {err}", parent.display());
+    }
 
     Ok(())
 }
@@ -0,0 +1,234 @@
+//! A single RON document, like the `config.ron` of many apps.
+//!
+//! Values are written with the same serializer as `cosmic_ron`.
+//! When the file already exists, only the fields whose value changed are rewritten,
+//! so the comments between the fields of the root struct are kept.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    ops::Range,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use figment::{
+    value::{Dict, Tag, Value},
+    Metadata, Profile, Provider,
+};
+use serde::de::Error;
+
+use crate::node::{Node, NodeContainer, NodeObject};
+
+use super::ron_value::{self, Document, RonValue};
+
+pub struct RonFileProvider {
+    path: PathBuf,
+}
+
+impl RonFileProvider {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Provider for RonFileProvider {
+    fn metadata(&self) -> figment::Metadata {
+        Metadata::named("ron provider")
+    }
+
+    fn data(
+        &self,
+    ) -> Result<figment::value::Map<figment::Profile, figment::value::Dict>, figment::Error> {
+        self.data_impl().map_err(figment::Error::custom)
+    }
+}
+
+impl RonFileProvider {
+    fn data_impl(
+        &self,
+    ) -> anyhow::Result<figment::value::Map<figment::Profile, figment::value::Dict>> {
+        let content = fs::read_to_string(&self.path)?;
+
+        let document = ron_value::document_from_str(&content)
+            .map_err(|e| anyhow!("{}: {e}", self.path.display()))?;
+
+        let dict = document
+            .fields
+            .into_iter()
+            .map(|field| (field.key, field.value.to_value()))
+            .collect::<Dict>();
+
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+/// The fields of the document, as they were written
+pub fn read_ron(path: &Path) -> anyhow::Result<RonValue> {
+    let content = fs::read_to_string(path)?;
+
+    let fields = ron_value::document_from_str(&content)?
+        .fields
+        .into_iter()
+        .map(|field| (field.key, field.value))
+        .collect();
+
+    Ok(RonValue::Struct(fields))
+}
+
+pub fn to_string_edited(path: &Path, tree: &NodeContainer) -> anyhow::Result<String> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return to_string(tree),
+        Err(err) => return Err(err.into()),
+    };
+
+    let Node::Object(node_object) = &tree.node else {
+        bail!("the root of a RON document must be a struct");
+    };
+
+    // the values of a file we can't parse were not loaded, rewriting it would lose them
+    let document =
+        ron_value::document_from_str(&content).map_err(|e| anyhow!("{}: {e}", path.display()))?;
+
+    match edit(&content, &document, node_object)? {
+        Some(content) => Ok(content),
+        None => to_string(tree),
+    }
+}
+
+fn to_string(tree: &NodeContainer) -> anyhow::Result<String> {
+    Ok(ron::ser::to_string_pretty(
+        tree,
+        ron::ser::PrettyConfig::new(),
+    )?)
+}
+
+/// Return `None` when the document is too compact to be edited, like `(x: 1)`
+fn edit(
+    content: &str,
+    document: &Document,
+    node_object: &NodeObject,
+) -> anyhow::Result<Option<String>> {
+    let mut edits: Vec<(Range<usize>, String)> = Vec::new();
+    let mut written = HashSet::new();
+
+    for field in &document.fields {
+        let is_written = node_object
+            .nodes
+            .get(&field.key)
+            .is_some_and(|node| node.will_be_written())
+            || node_object.unknown.contains_key(&field.key);
+
+        // removed, or a duplicated field
+        if !is_written || !written.insert(field.key.as_str()) {
+            let end = field.comma_end.unwrap_or(field.value_end);
+            edits.push((
+                super::removal_range(content, field.start..end),
+                String::new(),
+            ));
+            continue;
+        }
+
+        if !is_same(node_object, &field.key, &field.value.to_value()) {
+            let value = to_ron(node_object, &field.key)?;
+            let indent = line_indent(content, field.start);
+
+            edits.push((
+                field.value_start..field.value_end,
+                value.replace('\n', &format!("\n{indent}")),
+            ));
+        }
+    }
+
+    let new_fields = node_object
+        .nodes
+        .iter()
+        .filter(|(_, node)| node.will_be_written())
+        .map(|(key, _)| key)
+        .chain(node_object.unknown.keys())
+        .filter(|key| !written.contains(key.as_str()))
+        .collect::<Vec<_>>();
+
+    if !new_fields.is_empty() {
+        // the closing parenthesis must be on its own line
+        let line_start = content[..document.end].rfind('\n').map_or(0, |pos| pos + 1);
+        if !content[line_start..document.end].trim().is_empty() {
+            return Ok(None);
+        }
+
+        let last = document
+            .fields
+            .iter()
+            .rev()
+            .find(|field| written.contains(field.key.as_str()));
+
+        let indent = last.map_or("    ", |field| line_indent(content, field.start));
+
+        if let Some(last) = last
+            && last.comma_end.is_none()
+        {
+            edits.push((last.value_end..last.value_end, ",".to_string()));
+        }
+
+        let mut text = String::new();
+
+        for key in new_fields {
+            let value = to_ron(node_object, key)?.replace('\n', &format!("\n{indent}"));
+            text.push_str(&format!("{indent}{key}: {value},\n"));
+        }
+
+        edits.push((line_start..line_start, text));
+    }
+
+    let mut content = content.to_string();
+
+    // from the end, so the positions of the other edits stay valid
+    edits.sort_by_key(|(range, _)| (range.start, range.end));
+    for (range, text) in edits.into_iter().rev() {
+        content.replace_range(range, &text);
+    }
+
+    Ok(Some(content))
+}
+
+/// Compare in the types of the schema, so `0x10` is the same as `16`
+fn is_same(node_object: &NodeObject, key: &str, value: &Value) -> bool {
+    match node_object.nodes.get(key) {
+        Some(node) => {
+            let mut old = node.clone();
+            old.remove_value_rec();
+
+            old.apply_value(value.clone(), true).is_ok()
+                && old.to_value(&Tag::Default) == node.to_value(&Tag::Default)
+        }
+        None => node_object.unknown.get(key) == Some(value),
+    }
+}
+
+fn to_ron(node_object: &NodeObject, key: &str) -> anyhow::Result<String> {
+    let config = ron::ser::PrettyConfig::new();
+
+    let value = match node_object.nodes.get(key) {
+        Some(node) => ron::ser::to_string_pretty(node, config)?,
+        None => match node_object.unknown_ron(key) {
+            Some(value) => ron::ser::to_string_pretty(value, config)?,
+            None => ron::ser::to_string_pretty(&node_object.unknown[key], config)?,
+        },
+    };
+
+    Ok(value)
+}
+
+fn line_indent(content: &str, pos: usize) -> &str {
+    let line_start = content[..pos].rfind('\n').map_or(0, |pos| pos + 1);
+    let indent = &content[line_start..pos];
+
+    if indent.trim().is_empty() {
+        indent
+    } else {
+        ""
+    }
+}
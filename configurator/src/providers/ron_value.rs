@@ -0,0 +1,862 @@
+//! Parse RON into a figment [`Value`].
+//!
+//! `ron::Value` drops the name of enum variants: `A` and `B(1)` are read as `()` and `1`.
+//! Here, variants are kept the way serde represents externally tagged enums
+//! in self describing formats, so the node tree can find the right variant:
+//! - `A` becomes the string `"A"`
+//! - `B(1)`, `B(1, 2)` and `B(x: 1)` become `{ "B": .. }`
+//! - `Some(x)` becomes `x` and `None` becomes [`Empty::None`]
+//!
+//! Struct names are assumed to be absent, like in files written by cosmic-config.
+//!
+//! The values are first read as a [`RonValue`], which keeps what figment can't represent,
+//! so the values unknown to the schema can be written back the way they were read.
+
+use anyhow::{anyhow, bail};
+use figment::value::{Dict, Empty, Num, Tag, Value};
+use serde::{
+    ser::{
+        SerializeMap, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleVariant,
+    },
+    Serialize,
+};
+
+use crate::node::intern;
+
+pub fn from_str(str: &str) -> anyhow::Result<Value> {
+    ron_from_str(str).map(|value| value.to_value())
+}
+
+pub fn ron_from_str(str: &str) -> anyhow::Result<RonValue> {
+    let mut parser = Parser { str, pos: 0 };
+
+    parser.skip_ws()?;
+    parser.skip_attributes()?;
+
+    let value = parser.value()?;
+
+    parser.skip_ws()?;
+    if parser.pos != str.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(value)
+}
+
+/// A RON value, with the enum variants and the `Some` that figment values lose
+#[derive(Debug, Clone, PartialEq)]
+pub enum RonValue {
+    Unit,
+    Bool(bool),
+    Num(Num),
+    String(String),
+    Char(char),
+    Option(Option<Box<RonValue>>),
+    List(Vec<RonValue>),
+    /// The key as figment reads it, the key, and the value
+    Map(Vec<(String, RonValue, RonValue)>),
+    Tuple(Vec<RonValue>),
+    /// `(x)`
+    Newtype(Box<RonValue>),
+    Struct(Vec<(String, RonValue)>),
+    UnitVariant(String),
+    /// The content of the parentheses: `B(1)` is a [`RonValue::Newtype`],
+    /// `B(1, 2)` a [`RonValue::Tuple`] and `B(x: 1)` a [`RonValue::Struct`]
+    Variant(String, Box<RonValue>),
+}
+
+impl RonValue {
+    pub fn to_value(&self) -> Value {
+        let tag = Tag::Default;
+
+        match self {
+            RonValue::Unit => Value::Empty(tag, Empty::Unit),
+            RonValue::Bool(value) => Value::Bool(tag, *value),
+            RonValue::Num(num) => Value::Num(tag, *num),
+            RonValue::String(value) => Value::String(tag, value.clone()),
+            RonValue::Char(value) => Value::String(tag, value.to_string()),
+            RonValue::Option(None) => Value::Empty(tag, Empty::None),
+            RonValue::Option(Some(value)) | RonValue::Newtype(value) => value.to_value(),
+            RonValue::List(values) | RonValue::Tuple(values) => {
+                Value::Array(tag, values.iter().map(RonValue::to_value).collect())
+            }
+            RonValue::Map(entries) => Value::Dict(
+                tag,
+                entries
+                    .iter()
+                    .map(|(key, _, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+            RonValue::Struct(fields) => Value::Dict(
+                tag,
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_value()))
+                    .collect(),
+            ),
+            RonValue::UnitVariant(name) => Value::String(tag, name.clone()),
+            RonValue::Variant(name, inner) => {
+                let mut dict = Dict::new();
+                dict.insert(name.clone(), inner.to_value());
+                Value::Dict(tag, dict)
+            }
+        }
+    }
+
+    /// The value of a field of a struct, of an entry of a map, or the data of a variant
+    pub fn get(&self, key: &str) -> Option<&RonValue> {
+        match self {
+            RonValue::Variant(name, inner) if name == key => Some(inner),
+            RonValue::Struct(fields) => fields
+                .iter()
+                .rev()
+                .find(|(field_key, _)| field_key == key)
+                .map(|(_, value)| value),
+            RonValue::Map(entries) => entries
+                .iter()
+                .rev()
+                .find(|(entry_key, ..)| entry_key == key)
+                .map(|(.., value)| value),
+            _ => None,
+        }
+    }
+}
+
+impl Serialize for RonValue {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match self {
+            RonValue::Unit => ser.serialize_unit(),
+            RonValue::Bool(value) => ser.serialize_bool(*value),
+            RonValue::Num(num) => num.serialize(ser),
+            RonValue::String(value) => ser.serialize_str(value),
+            RonValue::Char(value) => ser.serialize_char(*value),
+            RonValue::Option(None) => ser.serialize_none(),
+            RonValue::Option(Some(value)) => ser.serialize_some(value),
+            RonValue::List(values) => ser.collect_seq(values),
+            RonValue::Map(entries) => {
+                let mut map = ser.serialize_map(Some(entries.len()))?;
+
+                for (_, key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+
+                map.end()
+            }
+            RonValue::Tuple(values) => {
+                let mut tuple = ser.serialize_tuple(values.len())?;
+
+                for value in values {
+                    tuple.serialize_element(value)?;
+                }
+
+                tuple.end()
+            }
+            RonValue::Newtype(value) => ser.serialize_newtype_struct("", value),
+            RonValue::Struct(fields) => {
+                let mut map = ser.serialize_struct("", fields.len())?;
+
+                for (key, value) in fields {
+                    map.serialize_field(intern(key), value)?;
+                }
+
+                map.end()
+            }
+            RonValue::UnitVariant(name) => ser.serialize_unit_variant("", 0, intern(name)),
+            RonValue::Variant(name, inner) => match &**inner {
+                RonValue::Unit => ser.serialize_tuple_variant("", 0, intern(name), 0)?.end(),
+                RonValue::Tuple(values) => {
+                    let mut tuple =
+                        ser.serialize_tuple_variant("", 0, intern(name), values.len())?;
+
+                    for value in values {
+                        tuple.serialize_field(value)?;
+                    }
+
+                    tuple.end()
+                }
+                RonValue::Struct(fields) => {
+                    let mut map =
+                        ser.serialize_struct_variant("", 0, intern(name), fields.len())?;
+
+                    for (key, value) in fields {
+                        map.serialize_field(intern(key), value)?;
+                    }
+
+                    map.end()
+                }
+                RonValue::Newtype(value) => {
+                    ser.serialize_newtype_variant("", 0, intern(name), value)
+                }
+                value => ser.serialize_newtype_variant("", 0, intern(name), value),
+            },
+        }
+    }
+}
+
+/// A document whose root is a struct, like the `config.ron` of many apps
+pub struct Document {
+    pub fields: Vec<Field>,
+    /// Position of the closing parenthesis of the root
+    pub end: usize,
+}
+
+pub struct Field {
+    pub key: String,
+    pub value: RonValue,
+    /// Position of the key
+    pub start: usize,
+    pub value_start: usize,
+    pub value_end: usize,
+    /// Position after the comma that follows the value
+    pub comma_end: Option<usize>,
+}
+
+/// Parse a document whose root is a struct, and remember where its fields are.
+/// The struct can be named, like `Config(x: 1)`.
+pub fn document_from_str(str: &str) -> anyhow::Result<Document> {
+    let mut parser = Parser { str, pos: 0 };
+
+    parser.skip_ws()?;
+    parser.skip_attributes()?;
+
+    if parser.peek().is_some_and(is_ident_start) {
+        parser.identifier()?;
+        parser.skip_ws()?;
+    }
+
+    parser.expect('(')?;
+    parser.skip_ws()?;
+
+    let mut fields = Vec::new();
+
+    while parser.peek() != Some(')') {
+        let start = parser.pos;
+        let key = parser.identifier()?.to_string();
+        parser.skip_ws()?;
+        parser.expect(':')?;
+        parser.skip_ws()?;
+
+        let value_start = parser.pos;
+        let value = parser.value()?;
+        let value_end = parser.pos;
+
+        parser.skip_ws()?;
+        let comma_end = parser.consume(",").then_some(parser.pos);
+        parser.skip_ws()?;
+
+        fields.push(Field {
+            key,
+            value,
+            start,
+            value_start,
+            value_end,
+            comma_end,
+        });
+
+        if comma_end.is_none() {
+            break;
+        }
+    }
+
+    let end = parser.pos;
+    parser.expect(')')?;
+
+    parser.skip_ws()?;
+    if parser.pos != str.len() {
+        return Err(parser.error("trailing characters"));
+    }
+
+    Ok(Document { fields, end })
+}
+
+struct Parser<'a> {
+    str: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.str[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn consume(&mut self, str: &str) -> bool {
+        if self.rest().starts_with(str) {
+            self.pos += str.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> anyhow::Result<()> {
+        if self.next() == Some(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn error(&self, msg: &str) -> anyhow::Error {
+        let before = &self.str[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.len() - before.rfind('\n').map_or(0, |pos| pos + 1) + 1;
+
+        anyhow!("{msg} at {line}:{column}")
+    }
+
+    fn skip_ws(&mut self) -> anyhow::Result<()> {
+        loop {
+            let rest = self.rest();
+            self.pos += rest.len() - rest.trim_start().len();
+
+            if self.consume("//") {
+                match self.rest().find('\n') {
+                    Some(end) => self.pos += end,
+                    None => self.pos = self.str.len(),
+                }
+            } else if self.consume("/*") {
+                let mut depth = 1;
+
+                while depth > 0 {
+                    if self.consume("/*") {
+                        depth += 1;
+                    } else if self.consume("*/") {
+                        depth -= 1;
+                    } else if self.next().is_none() {
+                        bail!(self.error("unterminated block comment"));
+                    }
+                }
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    /// `#![enable(..)]` extensions don't change how values are read here
+    fn skip_attributes(&mut self) -> anyhow::Result<()> {
+        while self.consume("#![") {
+            match self.rest().find(']') {
+                Some(end) => self.pos += end + 1,
+                None => bail!(self.error("unterminated attribute")),
+            }
+            self.skip_ws()?;
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> anyhow::Result<RonValue> {
+        let value = match self.peek() {
+            None => bail!(self.error("unexpected end of input")),
+            Some('"') => RonValue::String(self.string()?),
+            Some('r') if self.is_raw_string() => RonValue::String(self.raw_string()?),
+            Some('\'') => RonValue::Char(self.char()?),
+            Some('[') => RonValue::List(self.list()?),
+            Some('{') => RonValue::Map(self.map()?),
+            Some('(') => self.parenthesized()?,
+            Some(c) if c.is_ascii_digit() || matches!(c, '+' | '-' | '.') => self.number()?,
+            Some(c) if is_ident_start(c) => {
+                let ident = self.identifier()?;
+
+                match ident {
+                    "true" => RonValue::Bool(true),
+                    "false" => RonValue::Bool(false),
+                    "None" => RonValue::Option(None),
+                    "inf" => RonValue::Num(Num::F64(f64::INFINITY)),
+                    "NaN" => RonValue::Num(Num::F64(f64::NAN)),
+                    "Some" => {
+                        self.skip_ws()?;
+                        self.expect('(')?;
+                        self.skip_ws()?;
+                        let value = self.value()?;
+                        self.skip_ws()?;
+                        self.consume(",");
+                        self.skip_ws()?;
+                        self.expect(')')?;
+                        RonValue::Option(Some(Box::new(value)))
+                    }
+                    ident => {
+                        let ident = ident.to_string();
+
+                        self.skip_ws()?;
+                        if self.peek() == Some('(') {
+                            RonValue::Variant(ident, Box::new(self.parenthesized()?))
+                        } else {
+                            RonValue::UnitVariant(ident)
+                        }
+                    }
+                }
+            }
+            Some(c) => bail!(self.error(&format!("unexpected character '{c}'"))),
+        };
+
+        Ok(value)
+    }
+
+    /// `()`, `(x)`, `(a, b)` or `(key: value)`
+    ///
+    /// A single element without a trailing comma is a newtype.
+    fn parenthesized(&mut self) -> anyhow::Result<RonValue> {
+        self.expect('(')?;
+        self.skip_ws()?;
+
+        if self.consume(")") {
+            return Ok(RonValue::Unit);
+        }
+
+        if self.is_struct_field() {
+            let mut fields = Vec::new();
+
+            loop {
+                let key = self.identifier()?.to_string();
+                self.skip_ws()?;
+                self.expect(':')?;
+                self.skip_ws()?;
+                fields.push((key, self.value()?));
+                self.skip_ws()?;
+
+                if self.consume(",") {
+                    self.skip_ws()?;
+                    if self.consume(")") {
+                        break;
+                    }
+                } else {
+                    self.expect(')')?;
+                    break;
+                }
+            }
+
+            return Ok(RonValue::Struct(fields));
+        }
+
+        let mut values = Vec::new();
+        let mut trailing_comma = false;
+
+        loop {
+            values.push(self.value()?);
+            self.skip_ws()?;
+
+            if self.consume(",") {
+                self.skip_ws()?;
+                if self.consume(")") {
+                    trailing_comma = true;
+                    break;
+                }
+            } else {
+                self.expect(')')?;
+                break;
+            }
+        }
+
+        if values.len() == 1 && !trailing_comma {
+            Ok(RonValue::Newtype(Box::new(values.remove(0))))
+        } else {
+            Ok(RonValue::Tuple(values))
+        }
+    }
+
+    fn is_struct_field(&mut self) -> bool {
+        let start = self.pos;
+
+        let res = self.identifier().is_ok()
+            && self.skip_ws().is_ok()
+            && self.rest().starts_with(':')
+            && !self.rest().starts_with("::");
+
+        self.pos = start;
+        res
+    }
+
+    fn list(&mut self) -> anyhow::Result<Vec<RonValue>> {
+        let mut values = Vec::new();
+
+        self.expect('[')?;
+        self.skip_ws()?;
+
+        while !self.consume("]") {
+            values.push(self.value()?);
+            self.skip_ws()?;
+
+            if self.consume(",") {
+                self.skip_ws()?;
+            } else {
+                self.expect(']')?;
+                break;
+            }
+        }
+
+        Ok(values)
+    }
+
+    fn map(&mut self) -> anyhow::Result<Vec<(String, RonValue, RonValue)>> {
+        let mut entries = Vec::new();
+
+        self.expect('{')?;
+        self.skip_ws()?;
+
+        while !self.consume("}") {
+            let start = self.pos;
+            let key_value = self.value()?;
+
+            let key = match &key_value {
+                RonValue::String(key) | RonValue::UnitVariant(key) => key.clone(),
+                RonValue::Char(key) => key.to_string(),
+                RonValue::Num(..) | RonValue::Bool(..) => self.str[start..self.pos].to_string(),
+                _ => bail!(self.error("unsupported map key")),
+            };
+
+            self.skip_ws()?;
+            self.expect(':')?;
+            self.skip_ws()?;
+            entries.push((key, key_value, self.value()?));
+            self.skip_ws()?;
+
+            if self.consume(",") {
+                self.skip_ws()?;
+            } else {
+                self.expect('}')?;
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    fn identifier(&mut self) -> anyhow::Result<&str> {
+        // raw identifier
+        self.consume("r#");
+
+        let start = self.pos;
+
+        match self.peek() {
+            Some(c) if is_ident_start(c) => {}
+            _ => bail!(self.error("expected an identifier")),
+        }
+
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(self.rest().len());
+        self.pos += len;
+
+        Ok(&self.str[start..self.pos])
+    }
+
+    fn number(&mut self) -> anyhow::Result<RonValue> {
+        let negative = self.consume("-");
+        if !negative {
+            self.consume("+");
+        }
+
+        if self.consume("inf") {
+            let inf = if negative {
+                f64::NEG_INFINITY
+            } else {
+                f64::INFINITY
+            };
+            return Ok(RonValue::Num(Num::F64(inf)));
+        }
+        if self.consume("NaN") {
+            return Ok(RonValue::Num(Num::F64(f64::NAN)));
+        }
+
+        let radix = if self.consume("0x") {
+            16
+        } else if self.consume("0o") {
+            8
+        } else if self.consume("0b") {
+            2
+        } else {
+            10
+        };
+
+        let start = self.pos;
+        let mut prev = None;
+
+        while let Some(c) = self.peek() {
+            let is_exponent_sign =
+                radix == 10 && matches!(c, '+' | '-') && matches!(prev, Some('e' | 'E'));
+
+            if !(c.is_ascii_alphanumeric() || c == '_' || c == '.' || is_exponent_sign) {
+                break;
+            }
+
+            prev = self.next();
+        }
+
+        let digits = self.str[start..self.pos].replace('_', "");
+
+        let is_float = radix == 10 && digits.contains(['.', 'e', 'E']);
+
+        let num = if is_float {
+            let value = digits
+                .parse::<f64>()
+                .map_err(|e| self.error(&format!("invalid float: {e}")))?;
+
+            Num::F64(if negative { -value } else { value })
+        } else {
+            let magnitude = u128::from_str_radix(&digits, radix)
+                .map_err(|e| self.error(&format!("invalid integer: {e}")))?;
+
+            if negative {
+                let value = 0i128
+                    .checked_sub_unsigned(magnitude)
+                    .ok_or_else(|| self.error("integer overflow"))?;
+
+                match i64::try_from(value) {
+                    Ok(value) => Num::I64(value),
+                    Err(_) => Num::I128(value),
+                }
+            } else {
+                match u64::try_from(magnitude) {
+                    Ok(value) => Num::U64(value),
+                    Err(_) => Num::U128(magnitude),
+                }
+            }
+        };
+
+        Ok(RonValue::Num(num))
+    }
+
+    fn string(&mut self) -> anyhow::Result<String> {
+        let mut string = String::new();
+
+        self.expect('"')?;
+
+        loop {
+            match self.next() {
+                Some('"') => return Ok(string),
+                Some('\\') => string.push(self.escape()?),
+                Some(c) => string.push(c),
+                None => bail!(self.error("unterminated string")),
+            }
+        }
+    }
+
+    fn is_raw_string(&self) -> bool {
+        self.rest()
+            .strip_prefix('r')
+            .is_some_and(|rest| rest.trim_start_matches('#').starts_with('"'))
+    }
+
+    fn raw_string(&mut self) -> anyhow::Result<String> {
+        self.expect('r')?;
+
+        let hashes = self.rest().len() - self.rest().trim_start_matches('#').len();
+        self.pos += hashes;
+        self.expect('"')?;
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+
+        match self.rest().find(&terminator) {
+            Some(end) => {
+                let string = self.rest()[..end].to_string();
+                self.pos += end + terminator.len();
+                Ok(string)
+            }
+            None => bail!(self.error("unterminated raw string")),
+        }
+    }
+
+    fn char(&mut self) -> anyhow::Result<char> {
+        self.expect('\'')?;
+
+        let c = match self.next() {
+            Some('\\') => self.escape()?,
+            Some(c) => c,
+            None => bail!(self.error("unterminated char")),
+        };
+
+        self.expect('\'')?;
+
+        Ok(c)
+    }
+
+    fn escape(&mut self) -> anyhow::Result<char> {
+        let c = match self.next() {
+            Some('\'') => '\'',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') => '\0',
+            Some('x') => {
+                let hex = self.rest().get(..2).unwrap_or_default();
+
+                let c = u8::from_str_radix(hex, 16)
+                    .ok()
+                    .filter(u8::is_ascii)
+                    .ok_or_else(|| self.error("invalid ascii escape"))?;
+                self.pos += 2;
+
+                c as char
+            }
+            Some('u') => {
+                self.expect('{')?;
+
+                let end = self
+                    .rest()
+                    .find('}')
+                    .ok_or_else(|| self.error("unterminated unicode escape"))?;
+
+                let hex = self.rest()[..end].replace('_', "");
+
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| self.error("invalid unicode escape"))?;
+                self.pos += end + 1;
+
+                c
+            }
+            _ => bail!(self.error("invalid escape")),
+        };
+
+        Ok(c)
+    }
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+#[cfg(test)]
+mod test {
+    use figment::value::{Dict, Empty, Num, Tag, Value};
+
+    use super::{document_from_str, from_str};
+
+    fn string(str: &str) -> Value {
+        Value::String(Tag::Default, str.into())
+    }
+
+    fn dict(entries: Vec<(&str, Value)>) -> Value {
+        let dict: Dict = entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect();
+
+        Value::Dict(Tag::Default, dict)
+    }
+
+    #[test]
+    fn test_enum_variants() {
+        assert_eq!(from_str("A").unwrap(), string("A"));
+
+        assert_eq!(
+            from_str("B(1)").unwrap(),
+            dict(vec![("B", Value::Num(Tag::Default, Num::U64(1)))])
+        );
+
+        assert_eq!(
+            from_str("C((x: \"hello\", y: -1))").unwrap(),
+            dict(vec![(
+                "C",
+                dict(vec![
+                    ("x", string("hello")),
+                    ("y", Value::Num(Tag::Default, Num::I64(-1)))
+                ])
+            )])
+        );
+
+        assert_eq!(
+            from_str("D(a: [A, B(2)])").unwrap(),
+            dict(vec![(
+                "D",
+                dict(vec![(
+                    "a",
+                    Value::Array(
+                        Tag::Default,
+                        vec![
+                            string("A"),
+                            dict(vec![("B", Value::Num(Tag::Default, Num::U64(2)))])
+                        ]
+                    )
+                )])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_misc() {
+        let value = from_str(
+            r##"#![enable(implicit_some)]
+            // comment
+            (
+                a: Some(None), /* nested /* comment */ */
+                b: (1.5e3, 0x_ff, 'c',),
+                c: { "k": r#"raw "str""#, 1: "\u{e9}\n" },
+                d: (),
+            )"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value,
+            dict(vec![
+                ("a", Value::Empty(Tag::Default, Empty::None)),
+                (
+                    "b",
+                    Value::Array(
+                        Tag::Default,
+                        vec![
+                            Value::Num(Tag::Default, Num::F64(1500.)),
+                            Value::Num(Tag::Default, Num::U64(255)),
+                            string("c"),
+                        ]
+                    )
+                ),
+                (
+                    "c",
+                    dict(vec![("k", string("raw \"str\"")), ("1", string("é\n"))])
+                ),
+                ("d", Value::Empty(Tag::Default, Empty::Unit)),
+            ])
+        );
+
+        assert!(from_str("(a: 1").is_err());
+        assert!(from_str("[1, 2] 3").is_err());
+    }
+
+    #[test]
+    fn test_document() {
+        let str = "// comment\nConfig(\n    a: (1, 2), // a\n    b: \"b\"\n)\n";
+
+        let document = document_from_str(str).unwrap();
+
+        let fields = document
+            .fields
+            .iter()
+            .map(|field| {
+                (
+                    &str[field.start..field.value_start],
+                    &str[field.value_start..field.value_end],
+                    field.comma_end.is_some(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fields,
+            vec![("a: ", "(1, 2)", true), ("b: ", "\"b\"", false)]
+        );
+        assert_eq!(&str[document.end..], ")\n");
+
+        assert!(document_from_str("()").unwrap().fields.is_empty());
+        assert!(document_from_str("[1]").is_err());
+    }
+}
@@ -2,40 +2,72 @@ use std::{collections::HashMap, fs, marker::PhantomData, path::Path, sync::LazyL
 
 use configurator_utils::ConfigFormat;
 use figment::{
-    value::{Tag, Value},
+    value::{Dict, Tag, Value},
     Profile, Provider,
 };
-use serde::Serialize;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
 use serial_test::serial;
 
-use crate::test_common::*;
+use crate::{
+    node::{data_path::DataPathType, Node, NodeContainer},
+    test_common::*,
+    utils::figment_value_from_str,
+};
 
 use pretty_assertions::assert_eq;
 
 /// 1. write the value
 /// 2. read the value and assert equal
-fn write_and_read<P: AsRef<Path>>(path: P, format: &ConfigFormat, initial_value: &Value) {
+fn write_and_read<P: AsRef<Path>>(path: P, format: &ConfigFormat, tree: &NodeContainer) {
     let _ = fs::remove_dir_all(path.as_ref());
+    // the formats edited in place are a single file
+    let _ = fs::remove_file(path.as_ref());
 
-    super::write(path.as_ref(), format, initial_value).unwrap();
+    super::write(path.as_ref(), format, tree, &[]).unwrap();
 
-    let value = super::read_from_format(path.as_ref(), format);
+    let value = super::read_from_format(path.as_ref(), format, tree);
 
     let value = value.data().unwrap().remove(&Profile::Default).unwrap();
 
     let value = Value::Dict(Tag::Default, value);
 
-    assert_eq!(initial_value, &value);
-}
+    // apply to the tree to normalize the number types
+    let mut read_tree = tree.clone();
+    read_tree.remove_value_rec();
+    read_tree.apply_value(value, true).unwrap();
 
-fn write_and_read_common<S: Default + Serialize>(format: &ConfigFormat) {
-    write_and_read(
-        "tests/cosmic_ron/config1",
-        format,
-        &Value::serialize(S::default()).unwrap(),
+    assert_eq!(
+        tree.to_value(&Tag::Default).unwrap(),
+        read_tree.to_value(&Tag::Default).unwrap()
     );
 }
 
+fn tree_from_default<S: JsonSchema + Default + Serialize>() -> NodeContainer {
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(S));
+
+    tree.apply_value(Value::serialize(S::default()).unwrap(), true)
+        .unwrap();
+
+    tree
+}
+
+fn write_and_read_common<S: JsonSchema + Default + Serialize>(format: &ConfigFormat) {
+    let path = match format {
+        ConfigFormat::Json => "tests/json/config1.json",
+        ConfigFormat::CosmicRon => "tests/cosmic_ron/config1",
+        ConfigFormat::Toml => "tests/toml/config1.toml",
+        ConfigFormat::Yaml => "tests/yaml/config1.yaml",
+        ConfigFormat::Kdl => "tests/kdl/config1.kdl",
+        ConfigFormat::Keyfile => "tests/keyfile/config1.ini",
+        ConfigFormat::Ron => "tests/ron/config1.ron",
+        ConfigFormat::Jsonc => "tests/jsonc/config1.jsonc",
+        ConfigFormat::Json5 => "tests/json5/config1.json5",
+    };
+
+    write_and_read(path, format, &tree_from_default::<S>());
+}
+
 #[test]
 #[serial]
 fn test_bool_ron() {
@@ -60,6 +92,18 @@ fn test_float_ron() {
     write_and_read_common::<TestFloat>(&ConfigFormat::CosmicRon);
 }
 
+#[test]
+#[serial]
+fn test_precision_ron() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::CosmicRon);
+}
+
+#[test]
+#[serial]
+fn test_fixed_ron() {
+    write_and_read_common::<TestFixed>(&ConfigFormat::CosmicRon);
+}
+
 #[test]
 #[serial]
 fn test_enum_simple_ron() {
@@ -84,6 +128,125 @@ fn test_option_complex_ron() {
     write_and_read_common::<TestOptionComplex>(&ConfigFormat::CosmicRon);
 }
 
+#[test]
+#[serial]
+fn test_option_some_ron() {
+    write_and_read_common::<TestOptionSome>(&ConfigFormat::CosmicRon);
+}
+
+#[test]
+#[serial]
+fn test_option_some_json() {
+    let path = "tests/json/config3.json";
+
+    write_and_read(
+        path,
+        &ConfigFormat::Json,
+        &tree_from_default::<TestOptionSome>(),
+    );
+
+    let content = fs::read_to_string(path).unwrap();
+    assert!(content.contains("\"y\": null"));
+}
+
+#[test]
+#[serial]
+fn test_precision_json() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::Json);
+}
+
+#[test]
+#[serial]
+fn test_unknown_keys_json() {
+    let path = "tests/json/config4.json";
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestOptionSome));
+
+    let value = figment_value_from_str(
+        r#"{ "x": { "x": "a", "y": 1, "z": [1, "b"] }, "y": null, "unknown": { "a": true } }"#,
+    )
+    .unwrap();
+    tree.apply_value(value, true).unwrap();
+
+    write_and_read(path, &ConfigFormat::Json, &tree);
+
+    let content = fs::read_to_string(path).unwrap();
+    assert!(content.contains("\"unknown\""));
+    assert!(content.contains("\"z\""));
+}
+
+#[test]
+#[serial]
+fn test_unknown_keys_ron() {
+    let path = "tests/cosmic_ron/config1";
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestOptionSome));
+
+    let value = figment_value_from_str(r#"{ "x": { "x": "a", "y": 1, "z": [1, "b"] } }"#).unwrap();
+    tree.apply_value(value, true).unwrap();
+
+    write_and_read(path, &ConfigFormat::CosmicRon, &tree);
+}
+
+#[test]
+#[serial]
+fn test_nested_unknown_values_ron() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum E {
+        A,
+        B(i32),
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Inner {
+        p: E,
+        q: (i32, i32),
+    }
+
+    #[derive(Deserialize, Debug)]
+    struct Full {
+        y: i32,
+        u: Option<E>,
+        v: Inner,
+    }
+
+    let read_path = Path::new("tests/cosmic_ron/config4");
+    let write_path = Path::new("tests/cosmic_ron/config5");
+
+    let _ = fs::remove_dir_all(read_path);
+    let _ = fs::remove_dir_all(write_path);
+    fs::create_dir_all(read_path).unwrap();
+    fs::write(
+        read_path.join("x"),
+        r#"Some((x: "a", y: 1, u: Some(B(1)), v: (p: A, q: (1, 2))))"#,
+    )
+    .unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestOptionSome));
+
+    let read = super::read_config(read_path, &ConfigFormat::CosmicRon, &tree);
+    let value = read
+        .provider
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    tree.keep_unknown_ron(&read.ron.unwrap());
+
+    super::write(write_path, &ConfigFormat::CosmicRon, &tree, &[]).unwrap();
+
+    let content = fs::read_to_string(write_path.join("x")).unwrap();
+    let full: Option<Full> = ron::from_str(&content).unwrap();
+    let full = full.unwrap();
+
+    assert_eq!(full.y, 1);
+    assert_eq!(full.u, Some(E::B(1)));
+    assert_eq!(full.v, Inner { p: E::A, q: (1, 2) });
+}
+
 #[test]
 #[serial]
 fn test_tuple_ron() {
@@ -107,3 +270,961 @@ fn test_hash_map_ron() {
 fn test_very_complex_ron() {
     write_and_read_common::<TestVeryComplex>(&ConfigFormat::CosmicRon);
 }
+
+#[test]
+#[serial]
+fn test_any_ron() {
+    write_and_read_common::<TestAny>(&ConfigFormat::CosmicRon);
+}
+
+#[test]
+#[serial]
+fn test_very_complex_json() {
+    write_and_read(
+        "tests/json/config1.json",
+        &ConfigFormat::Json,
+        &tree_from_default::<TestVeryComplex>(),
+    );
+}
+
+#[test]
+#[serial]
+fn test_map_order_json() {
+    let path = "tests/json/config2.json";
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestHashMap));
+
+    let mut map = Dict::new();
+    map.insert("a".into(), Value::serialize(Complex::default()).unwrap());
+    map.insert("b".into(), Value::serialize(Complex::default()).unwrap());
+
+    let mut value = Dict::new();
+    value.insert("x".into(), Value::Dict(Tag::Default, map));
+
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut([DataPathType::Name("x".into())].iter())
+        .unwrap();
+    node.node.unwrap_object_mut().nodes.move_index(1, 0);
+
+    write_and_read(path, &ConfigFormat::Json, &tree);
+
+    let content = fs::read_to_string(path).unwrap();
+    assert!(content.find("\"b\"").unwrap() < content.find("\"a\"").unwrap());
+}
+
+#[test]
+#[serial]
+fn test_very_complex_toml() {
+    write_and_read(
+        "tests/toml/config1.toml",
+        &ConfigFormat::Toml,
+        &tree_from_default::<TestVeryComplex>(),
+    );
+}
+
+#[test]
+#[serial]
+fn test_edit_in_place_toml() {
+    let path = "tests/toml/config2.toml";
+
+    let content = r#"# my config
+
+# the second one
+[b]
+y = 2 # inline comment
+x = "b"
+
+[a]
+x = "a"
+y = 0x10
+"#;
+
+    fs::create_dir_all("tests/toml").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNested));
+
+    let value = super::read_from_format(path, &ConfigFormat::Toml, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("b".into()),
+                DataPathType::Name("y".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    node.apply_value(figment_value_from_str("3").unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Toml, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("y = 2", "y = 3")
+    );
+}
+
+#[test]
+#[serial]
+fn test_keep_f32_toml() {
+    let path = "tests/toml/config3.toml";
+    let content = "x = 1e-1 # comment\n";
+
+    fs::create_dir_all("tests/toml").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestFloat));
+
+    let value = super::read_from_format(path, &ConfigFormat::Toml, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Toml, &tree, &[]).unwrap();
+
+    assert_eq!(fs::read_to_string(path).unwrap(), content);
+}
+
+#[test]
+#[serial]
+fn test_delete_stale_keys_ron() {
+    let path = Path::new("tests/cosmic_ron/config2");
+
+    let _ = fs::remove_dir_all(path);
+    fs::create_dir_all(path).unwrap();
+    // not a key of the schema
+    fs::write(path.join("notes"), "keep me").unwrap();
+
+    let mut tree = tree_from_default::<TestNested>();
+    super::write(path, &ConfigFormat::CosmicRon, &tree, &[]).unwrap();
+    assert!(path.join("b").exists());
+
+    // e.g reset to default
+    tree.get_at_mut([DataPathType::Name("b".into())].iter())
+        .unwrap()
+        .modified = false;
+    super::write(path, &ConfigFormat::CosmicRon, &tree, &[]).unwrap();
+
+    assert!(path.join("a").exists());
+    assert!(!path.join("b").exists());
+    assert_eq!(fs::read_to_string(path.join("notes")).unwrap(), "keep me");
+}
+
+#[test]
+#[serial]
+fn test_keep_unreadable_keys_ron() {
+    let path = Path::new("tests/cosmic_ron/config6");
+
+    let _ = fs::remove_dir_all(path);
+    fs::create_dir_all(path).unwrap();
+    fs::write(path.join("a"), "(x: ").unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNested));
+    let read = super::read_config(path, &ConfigFormat::CosmicRon, &tree);
+    assert_eq!(read.file_errors.len(), 1);
+
+    let value = read
+        .provider
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let data_path = [
+        DataPathType::Name("b".into()),
+        DataPathType::Name("y".into()),
+    ];
+    tree.get_at_mut(data_path.iter())
+        .unwrap()
+        .apply_value(figment_value_from_str("3").unwrap(), true)
+        .unwrap();
+    tree.set_modified(data_path.iter());
+    super::write(path, &ConfigFormat::CosmicRon, &tree, &read.file_errors).unwrap();
+
+    assert!(path.join("b").exists());
+    assert_eq!(fs::read_to_string(path.join("a")).unwrap(), "(x: ");
+}
+
+#[test]
+#[serial]
+fn test_delete_removed_map_keys_ron() {
+    let path = Path::new("tests/cosmic_ron/config3");
+
+    let _ = fs::remove_dir_all(path);
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(HashMap<String, Complex>));
+    let map = HashMap::from([("k1", Complex::default()), ("k2", Complex::default())]);
+    tree.apply_value(Value::serialize(map).unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::CosmicRon, &tree, &[]).unwrap();
+    assert!(path.join("k2").exists());
+
+    let Node::Object(node_object) = &mut tree.node else {
+        panic!()
+    };
+    node_object.nodes.shift_remove("k2");
+    super::write(path, &ConfigFormat::CosmicRon, &tree, &[]).unwrap();
+
+    assert!(path.join("k1").exists());
+    assert!(!path.join("k2").exists());
+}
+
+#[test]
+#[serial]
+fn test_field_order_yaml() {
+    let path = "tests/yaml/config2.yaml";
+
+    let mut tree = tree_from_default::<TestVeryComplex>();
+
+    // not in the alphabetical order
+    tree.node.unwrap_object_mut().nodes.move_index(0, 2);
+
+    write_and_read(path, &ConfigFormat::Yaml, &tree);
+
+    let content = fs::read_to_string(path).unwrap();
+    assert!(content.starts_with("y:"), "{content}");
+}
+
+#[test]
+#[serial]
+fn test_bool_yaml() {
+    write_and_read_common::<TestBool>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_number_yaml() {
+    write_and_read_common::<TestNumber>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_float_yaml() {
+    write_and_read_common::<TestFloat>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_precision_yaml() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_fixed_yaml() {
+    write_and_read_common::<TestFixed>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_enum_complex_yaml() {
+    write_and_read_common::<TestEnumComplex>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_option_some_yaml() {
+    write_and_read_common::<TestOptionSome>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_tuple_yaml() {
+    write_and_read_common::<TestTuple>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_vec_yaml() {
+    write_and_read_common::<TestVec>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_hash_map_yaml() {
+    write_and_read_common::<TestHashMap>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_very_complex_yaml() {
+    write_and_read_common::<TestVeryComplex>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_any_yaml() {
+    write_and_read_common::<TestAny>(&ConfigFormat::Yaml);
+}
+
+#[test]
+#[serial]
+fn test_bool_kdl() {
+    write_and_read_common::<TestBool>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_number_kdl() {
+    write_and_read_common::<TestNumber>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_float_kdl() {
+    write_and_read_common::<TestFloat>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_fixed_kdl() {
+    write_and_read_common::<TestFixed>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_enum_complex_kdl() {
+    write_and_read_common::<TestEnumComplex>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_option_some_kdl() {
+    write_and_read_common::<TestOptionSome>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_vec_kdl() {
+    write_and_read_common::<TestVec>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_hash_map_kdl() {
+    write_and_read_common::<TestHashMap>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_very_complex_kdl() {
+    write_and_read_common::<TestVeryComplex>(&ConfigFormat::Kdl);
+}
+
+#[test]
+#[serial]
+fn test_edit_in_place_kdl() {
+    let path = "tests/kdl/config2.kdl";
+
+    let content = r#"// my config
+
+// the second one
+b {
+    y 2 // inline comment
+    x "b"
+}
+
+a x="a" y=0x10
+"#;
+
+    fs::create_dir_all("tests/kdl").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNested));
+
+    let value = super::read_from_format(path, &ConfigFormat::Kdl, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("b".into()),
+                DataPathType::Name("y".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    node.apply_value(figment_value_from_str("3").unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Kdl, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("y 2", "y 3")
+    );
+}
+
+#[test]
+#[serial]
+fn test_repeated_and_keyed_nodes_kdl() {
+    let path = "tests/kdl/config3.kdl";
+
+    let content = r#"output "eDP-1" {
+    mode "1920x1080@120.030"
+    scale 2.0
+}
+
+output "HDMI-A-1" {
+    mode "2560x1440"
+    scale 1.0
+}
+
+spawn-at-startup "waybar"
+spawn-at-startup "alacritty" "-e" "fish"
+
+window-rule {
+    match app-id="firefox"
+    match app-id="chromium"
+    open-maximized true
+}
+
+window-rule {
+    match app-id="mpv"
+    open-maximized false
+}
+"#;
+
+    fs::create_dir_all("tests/kdl").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNiri));
+
+    let value = super::read_from_format(path, &ConfigFormat::Kdl, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    let value = Value::Dict(Tag::Default, value);
+
+    let app_id = |app_id: &str| NiriMatch {
+        app_id: app_id.into(),
+    };
+
+    assert_eq!(
+        value.deserialize::<TestNiri>().unwrap(),
+        TestNiri {
+            output: HashMap::from([
+                (
+                    "eDP-1".into(),
+                    NiriOutput {
+                        mode: "1920x1080@120.030".into(),
+                        scale: 2.0,
+                    }
+                ),
+                (
+                    "HDMI-A-1".into(),
+                    NiriOutput {
+                        mode: "2560x1440".into(),
+                        scale: 1.0,
+                    }
+                ),
+            ]),
+            spawn_at_startup: vec![
+                vec!["waybar".into()],
+                vec!["alacritty".into(), "-e".into(), "fish".into()],
+            ],
+            window_rule: vec![
+                NiriWindowRule {
+                    matches: vec![app_id("firefox"), app_id("chromium")],
+                    open_maximized: true,
+                },
+                NiriWindowRule {
+                    matches: vec![app_id("mpv")],
+                    open_maximized: false,
+                },
+            ],
+        }
+    );
+
+    tree.apply_value(value, true).unwrap();
+
+    tree.get_at_mut(
+        [
+            DataPathType::Name("output".into()),
+            DataPathType::Name("eDP-1".into()),
+            DataPathType::Name("scale".into()),
+        ]
+        .iter(),
+    )
+    .unwrap()
+    .apply_value(figment_value_from_str("1.5").unwrap(), true)
+    .unwrap();
+
+    tree.get_at_mut([DataPathType::Name("spawn-at-startup".into())].iter())
+        .unwrap()
+        .apply_value(figment_value_from_str(r#"[["waybar"]]"#).unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Kdl, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content
+            .replace("scale 2.0", "scale 1.5")
+            .replace("spawn-at-startup \"alacritty\" \"-e\" \"fish\"\n", "")
+    );
+}
+
+#[test]
+#[serial]
+fn test_keep_f32_kdl() {
+    let path = "tests/kdl/config4.kdl";
+    let content = "x 1e-1 // comment\n";
+
+    fs::create_dir_all("tests/kdl").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestFloat));
+
+    let value = super::read_from_format(path, &ConfigFormat::Kdl, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Kdl, &tree, &[]).unwrap();
+
+    assert_eq!(fs::read_to_string(path).unwrap(), content);
+}
+
+#[test]
+#[serial]
+fn test_bool_keyfile() {
+    write_and_read_common::<TestBool>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_number_keyfile() {
+    write_and_read_common::<TestNumber>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_precision_keyfile() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_fixed_keyfile() {
+    write_and_read_common::<TestFixed>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_enum_simple_keyfile() {
+    write_and_read_common::<TestEnumSimple>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_nested_keyfile() {
+    write_and_read_common::<TestNested>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_desktop_file_keyfile() {
+    write_and_read_common::<TestDesktopFile>(&ConfigFormat::Keyfile);
+}
+
+#[test]
+#[serial]
+fn test_edit_in_place_keyfile() {
+    let path = "tests/keyfile/config2.desktop";
+
+    let content = r#"# my app
+[Desktop Entry]
+Name = Hello
+Name[fr]=Bonjour
+# a comment
+Terminal=false
+Categories=Utility;
+
+[Desktop Action new]
+Exec=app --new
+"#;
+
+    fs::create_dir_all("tests/keyfile").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestDesktopFile));
+
+    let value = super::read_from_format(path, &ConfigFormat::Keyfile, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("Desktop Entry".into()),
+                DataPathType::Name("Terminal".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    node.apply_value(Value::from(true), true).unwrap();
+
+    super::write(path, &ConfigFormat::Keyfile, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("Terminal=false", "Terminal=true")
+    );
+}
+
+#[test]
+#[serial]
+fn test_localized_keys_keyfile() {
+    let path = "tests/keyfile/config3.desktop";
+
+    let content = "[Desktop Entry]\nName=Hello\nName[fr]=Bonjour\nName[de]=Hallo\nX-Scale=1.5\n";
+
+    fs::create_dir_all("tests/keyfile").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestDesktopFile));
+    tree.enable_localized_keys();
+
+    let value = super::read_from_format(path, &ConfigFormat::Keyfile, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let entry = tree
+        .get_at_mut([DataPathType::Name("Desktop Entry".into())].iter())
+        .unwrap()
+        .node
+        .unwrap_object_mut();
+
+    assert!(entry.unknown.is_empty());
+    assert_eq!(
+        entry.nodes.keys().take(3).collect::<Vec<_>>(),
+        ["Name", "Name[de]", "Name[fr]"]
+    );
+    assert!(entry.nodes["Name[fr]"].removable);
+    assert_eq!(
+        entry.nodes["X-Scale"].to_value(&Tag::Default),
+        Some(Value::from(1.5f32))
+    );
+
+    entry.nodes.shift_remove("Name[de]");
+
+    super::write(path, &ConfigFormat::Keyfile, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("Name[de]=Hallo\n", "")
+    );
+}
+
+#[test]
+#[serial]
+fn test_bool_ron_file() {
+    write_and_read_common::<TestBool>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_float_ron_file() {
+    write_and_read_common::<TestFloat>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_precision_ron_file() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_fixed_ron_file() {
+    write_and_read_common::<TestFixed>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_enum_complex_ron_file() {
+    write_and_read_common::<TestEnumComplex>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_option_some_ron_file() {
+    write_and_read_common::<TestOptionSome>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_hash_map_ron_file() {
+    write_and_read_common::<TestHashMap>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_very_complex_ron_file() {
+    write_and_read_common::<TestVeryComplex>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_any_ron_file() {
+    write_and_read_common::<TestAny>(&ConfigFormat::Ron);
+}
+
+#[test]
+#[serial]
+fn test_edit_in_place_ron_file() {
+    let path = "tests/ron/config2.ron";
+
+    let content = r#"// my config
+(
+    // the second one
+    b: (
+        x: "b",
+        y: 2,
+    ),
+    a: (x: "a", y: 0x10), // inline comment
+)
+"#;
+
+    fs::create_dir_all("tests/ron").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNested));
+
+    let value = super::read_from_format(path, &ConfigFormat::Ron, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("b".into()),
+                DataPathType::Name("y".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    node.apply_value(figment_value_from_str("3").unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Ron, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("y: 2", "y: 3")
+    );
+}
+
+#[test]
+#[serial]
+fn test_keep_unparsable_ron_file() {
+    let path = "tests/ron/config3.ron";
+    let content = "(a: (x: \"a\", y: 1), b: ";
+
+    fs::create_dir_all("tests/ron").unwrap();
+    fs::write(path, content).unwrap();
+
+    let tree = tree_from_default::<TestNested>();
+
+    assert!(super::write(path, &ConfigFormat::Ron, &tree, &[]).is_err());
+    assert_eq!(fs::read_to_string(path).unwrap(), content);
+}
+
+#[test]
+#[serial]
+fn test_bool_jsonc() {
+    write_and_read_common::<TestBool>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_string_jsonc() {
+    write_and_read_common::<TestString>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_float_jsonc() {
+    write_and_read_common::<TestFloat>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_precision_jsonc() {
+    write_and_read_common::<TestPrecision>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_enum_complex_jsonc() {
+    write_and_read_common::<TestEnumComplex>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_option_some_jsonc() {
+    write_and_read_common::<TestOptionSome>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_very_complex_jsonc() {
+    write_and_read_common::<TestVeryComplex>(&ConfigFormat::Jsonc);
+}
+
+#[test]
+#[serial]
+fn test_option_json5() {
+    write_and_read_common::<TestOption>(&ConfigFormat::Json5);
+}
+
+#[test]
+#[serial]
+fn test_hash_map_json5() {
+    write_and_read_common::<TestHashMap>(&ConfigFormat::Json5);
+}
+
+#[test]
+#[serial]
+fn test_edit_in_place_json5() {
+    let path = "tests/json5/config2.json5";
+
+    let content = r#"// my config
+{
+    // the second one
+    b: {
+        x: 'b',
+        y: 2,
+    },
+    "a": { "x": "a", "y": 0x10 }, /* inline comment */
+}
+"#;
+
+    fs::create_dir_all("tests/json5").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestNested));
+
+    let value = super::read_from_format(path, &ConfigFormat::Json5, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    let node = tree
+        .get_at_mut(
+            [
+                DataPathType::Name("b".into()),
+                DataPathType::Name("y".into()),
+            ]
+            .iter(),
+        )
+        .unwrap();
+    node.apply_value(figment_value_from_str("3").unwrap(), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Json5, &tree, &[]).unwrap();
+
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("y: 2", "y: 3")
+    );
+}
+
+#[test]
+#[serial]
+fn test_non_finite_json5() {
+    let path = "tests/json5/config3.json5";
+
+    let content = "{\n  x: NaN,\n  y: 1.5,\n  z: 1,\n}\n";
+
+    fs::create_dir_all("tests/json5").unwrap();
+    fs::write(path, content).unwrap();
+
+    let mut tree = NodeContainer::from_json_schema(&schema_for!(TestPrecision));
+
+    let value = super::read_from_format(path, &ConfigFormat::Json5, &tree)
+        .data()
+        .unwrap()
+        .remove(&Profile::Default)
+        .unwrap();
+    tree.apply_value(Value::Dict(Tag::Default, value), true)
+        .unwrap();
+
+    // NaN is not rewritten
+    super::write(path, &ConfigFormat::Json5, &tree, &[]).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), content);
+
+    let node = tree
+        .get_at_mut([DataPathType::Name("y".into())].iter())
+        .unwrap();
+    node.apply_value(Value::from(f64::NEG_INFINITY), true)
+        .unwrap();
+
+    super::write(path, &ConfigFormat::Json5, &tree, &[]).unwrap();
+    assert_eq!(
+        fs::read_to_string(path).unwrap(),
+        content.replace("1.5", "-Infinity")
+    );
+
+    // JSONC has no way to write them
+    let path = "tests/jsonc/config3.jsonc";
+    let _ = fs::remove_file(path);
+    assert!(super::write(path, &ConfigFormat::Jsonc, &tree, &[]).is_err());
+}
+
+#[test]
+#[serial]
+fn test_keep_unparsable_jsonc() {
+    let path = "tests/jsonc/config4.jsonc";
+    let content = "{ \"a\": { \"x\": \"a\", \"y\": 1 }, \"b\": ";
+
+    fs::create_dir_all("tests/jsonc").unwrap();
+    fs::write(path, content).unwrap();
+
+    let tree = tree_from_default::<TestNested>();
+
+    assert!(super::write(path, &ConfigFormat::Jsonc, &tree, &[]).is_err());
+    assert_eq!(fs::read_to_string(path).unwrap(), content);
+}
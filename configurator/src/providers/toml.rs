@@ -0,0 +1,219 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, bail};
+use figment::value::{Dict, Num, Tag, Value};
+use toml_edit::{ArrayOfTables, DocumentMut, InlineTable, Item, Table, TableLike};
+
+use crate::{node::NodeContainer, utils::f32_to_f64};
+
+/// Update the document at `path` with the values of `tree`.
+/// Comments, key order and formatting of the keys that already exist are kept.
+pub fn to_string_edited(path: &Path, tree: &NodeContainer) -> anyhow::Result<String> {
+    let mut document = match fs::read_to_string(path) {
+        Ok(content) => content.parse::<DocumentMut>()?,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => DocumentMut::new(),
+        Err(err) => return Err(err.into()),
+    };
+
+    let dict = match tree.to_value(&Tag::Default) {
+        Some(Value::Dict(_, dict)) => dict,
+        Some(value) => bail!("the root of a TOML document must be a table: {value:?}"),
+        None => Dict::new(),
+    };
+
+    update_table(document.as_table_mut(), &dict, false)?;
+
+    Ok(document.to_string())
+}
+
+/// A table is written as a document, other values as inline TOML
+pub fn figment_value_to_string(value: &Value) -> anyhow::Result<String> {
+    match value {
+        Value::Dict(_, dict) => {
+            let mut document = DocumentMut::new();
+            update_table(document.as_table_mut(), dict, false)?;
+            Ok(document.to_string())
+        }
+        value => Ok(to_toml_value(value)?.to_string()),
+    }
+}
+
+/// New values of inline tables must be inline too
+fn update_table(table: &mut dyn TableLike, dict: &Dict, inline: bool) -> anyhow::Result<()> {
+    let stale = table
+        .iter()
+        .map(|(key, _)| key.to_string())
+        .filter(|key| !dict.get(key).is_some_and(is_written))
+        .collect::<Vec<_>>();
+
+    for key in stale {
+        table.remove(&key);
+    }
+
+    for (key, value) in dict.iter().filter(|(_, value)| is_written(value)) {
+        match table.get_mut(key) {
+            Some(item) => update_item(item, value)?,
+            None => {
+                let item = if inline {
+                    Item::Value(to_toml_value(value)?)
+                } else {
+                    new_item(value)?
+                };
+                table.insert(key, item);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn update_item(item: &mut Item, value: &Value) -> anyhow::Result<()> {
+    match (item, value) {
+        (Item::Table(table), Value::Dict(_, dict)) => update_table(table, dict, false)?,
+        (Item::ArrayOfTables(tables), Value::Array(_, values)) if is_array_of_dicts(values) => {
+            while tables.len() > values.len() {
+                tables.remove(tables.len() - 1);
+            }
+
+            for (pos, value) in values.iter().enumerate() {
+                let Value::Dict(_, dict) = value else {
+                    unreachable!()
+                };
+
+                match tables.get_mut(pos) {
+                    Some(table) => update_table(table, dict, false)?,
+                    None => {
+                        let mut table = Table::new();
+                        update_table(&mut table, dict, false)?;
+                        tables.push(table);
+                    }
+                }
+            }
+        }
+        (Item::Value(old), value) if old.is_inline_table() || !matches!(value, Value::Dict(..)) => {
+            update_value(old, value)?
+        }
+        (item, value) => *item = new_item(value)?,
+    }
+
+    Ok(())
+}
+
+/// Replace the value, but keep the whitespace and comments around it
+fn update_value(old: &mut toml_edit::Value, value: &Value) -> anyhow::Result<()> {
+    match (old, value) {
+        (toml_edit::Value::InlineTable(table), Value::Dict(_, dict)) => {
+            update_table(table, dict, true)
+        }
+        (toml_edit::Value::Array(array), Value::Array(_, values)) => {
+            while array.len() > values.len() {
+                array.remove(array.len() - 1);
+            }
+
+            for (pos, value) in values.iter().enumerate() {
+                match array.get_mut(pos) {
+                    Some(old) => update_value(old, value)?,
+                    None => array.push(to_toml_value(value)?),
+                }
+            }
+            Ok(())
+        }
+        (old, value) => {
+            let new = to_toml_value(value)?;
+
+            // don't touch values that are already equal, to keep their representation (e.g `0x10`)
+            if !toml_value_eq(old, &new) {
+                let decor = old.decor().clone();
+                *old = new;
+                *old.decor_mut() = decor;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// New tables are written as `[table]`, and arrays of tables as `[[array]]`
+fn new_item(value: &Value) -> anyhow::Result<Item> {
+    let item = match value {
+        Value::Dict(_, dict) => {
+            let mut table = Table::new();
+            update_table(&mut table, dict, false)?;
+            Item::Table(table)
+        }
+        Value::Array(_, values) if !values.is_empty() && is_array_of_dicts(values) => {
+            let mut tables = ArrayOfTables::new();
+
+            for value in values {
+                let Value::Dict(_, dict) = value else {
+                    unreachable!()
+                };
+
+                let mut table = Table::new();
+                update_table(&mut table, dict, false)?;
+                tables.push(table);
+            }
+
+            Item::ArrayOfTables(tables)
+        }
+        value => Item::Value(to_toml_value(value)?),
+    };
+
+    Ok(item)
+}
+
+fn to_toml_value(value: &Value) -> anyhow::Result<toml_edit::Value> {
+    let value = match value {
+        Value::String(_, str) => str.into(),
+        Value::Char(_, char) => char.to_string().into(),
+        Value::Bool(_, bool) => (*bool).into(),
+        Value::Num(_, num) => match *num {
+            Num::F32(float) => f32_to_f64(float).into(),
+            Num::F64(float) => float.into(),
+            num => {
+                let int = num
+                    .to_i128()
+                    .ok_or_else(|| anyhow!("{num:?} is too big for TOML"))?;
+
+                i64::try_from(int)
+                    .map_err(|_| anyhow!("{int} doesn't fit in a TOML integer"))?
+                    .into()
+            }
+        },
+        Value::Empty(..) => bail!("TOML has no null value"),
+        Value::Dict(_, dict) => {
+            let mut table = InlineTable::new();
+
+            for (key, value) in dict.iter().filter(|(_, value)| is_written(value)) {
+                table.insert(key, to_toml_value(value)?);
+            }
+
+            toml_edit::Value::InlineTable(table)
+        }
+        Value::Array(_, values) => values
+            .iter()
+            .map(to_toml_value)
+            .collect::<anyhow::Result<toml_edit::Array>>()?
+            .into(),
+    };
+
+    Ok(value)
+}
+
+fn toml_value_eq(a: &toml_edit::Value, b: &toml_edit::Value) -> bool {
+    match (a, b) {
+        (toml_edit::Value::String(a), toml_edit::Value::String(b)) => a.value() == b.value(),
+        (toml_edit::Value::Integer(a), toml_edit::Value::Integer(b)) => a.value() == b.value(),
+        (toml_edit::Value::Float(a), toml_edit::Value::Float(b)) => a.value() == b.value(),
+        (toml_edit::Value::Boolean(a), toml_edit::Value::Boolean(b)) => a.value() == b.value(),
+        _ => false,
+    }
+}
+
+/// TOML has no null: `None` is written by omitting the key
+fn is_written(value: &Value) -> bool {
+    !matches!(value, Value::Empty(..))
+}
+
+fn is_array_of_dicts(values: &[Value]) -> bool {
+    values.iter().all(|value| matches!(value, Value::Dict(..)))
+}
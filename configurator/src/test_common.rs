@@ -167,3 +167,166 @@ pub struct TestVeryComplex {
     y: EnumComplex,
     v: (Vec<EnumComplex>, HashMap<String, EnumComplex>),
 }
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestAny {
+    x: json::Value,
+}
+
+impl Default for TestAny {
+    fn default() -> Self {
+        Self {
+            x: json::json!({
+                "a": [true, "str"],
+                "b": { "c": null },
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestOptionSome {
+    x: Option<Complex>,
+    y: Option<String>,
+}
+
+impl Default for TestOptionSome {
+    fn default() -> Self {
+        Self {
+            x: Some(Complex::default()),
+            y: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TestNested {
+    a: Complex,
+    b: Complex,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestPrecision {
+    x: f32,
+    y: f64,
+    z: u128,
+}
+
+impl Default for TestPrecision {
+    fn default() -> Self {
+        Self {
+            x: 0.12345,
+            y: 0.1 + 0.2,
+            z: u128::MAX,
+        }
+    }
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestFixed {
+    color: (u8, u8, u8),
+    rect: [f32; 4],
+    c: char,
+}
+
+impl Default for TestFixed {
+    fn default() -> Self {
+        Self {
+            color: (255, 128, 0),
+            rect: [0.0, 0.5, 1.0, 1.5],
+            c: 'x',
+        }
+    }
+}
+
+/// The example of RECOMMENDATION.md: the default of `number` depends on
+/// whether `nested` is in the config
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestParentDefault {
+    nested: TestChildDefault,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestChildDefault {
+    number: u32,
+}
+
+impl Default for TestParentDefault {
+    fn default() -> Self {
+        Self {
+            nested: TestChildDefault { number: 0 },
+        }
+    }
+}
+
+impl Default for TestChildDefault {
+    fn default() -> Self {
+        Self { number: 1 }
+    }
+}
+
+/// A `.desktop` file
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct TestDesktopFile {
+    #[serde(rename = "Desktop Entry")]
+    entry: DesktopEntry,
+}
+
+#[derive(Clone, Debug, JsonSchema, Serialize, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+pub struct DesktopEntry {
+    name: String,
+    terminal: bool,
+    categories: Vec<String>,
+    #[serde(rename = "X-Scale")]
+    scale: f32,
+}
+
+impl Default for DesktopEntry {
+    fn default() -> Self {
+        Self {
+            name: " Hello\tworld".into(),
+            terminal: false,
+            categories: vec!["Utility".into(), "A;B".into()],
+            scale: 1.5,
+        }
+    }
+}
+
+/// A subset of niri's config
+#[derive(Clone, Debug, PartialEq, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TestNiri {
+    pub output: HashMap<String, NiriOutput>,
+    pub spawn_at_startup: Vec<Vec<String>>,
+    pub window_rule: Vec<NiriWindowRule>,
+}
+
+#[derive(Clone, Debug, PartialEq, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct NiriOutput {
+    pub mode: String,
+    pub scale: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NiriWindowRule {
+    #[serde(rename = "match")]
+    pub matches: Vec<NiriMatch>,
+    pub open_maximized: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, JsonSchema, Serialize, Deserialize, Default)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct NiriMatch {
+    pub app_id: String,
+}
@@ -1,4 +1,10 @@
-use figment::{value::Dict, Figment, Profile, Provider};
+use anyhow::{anyhow, bail};
+use configurator_utils::ConfigFormat;
+use figment::{
+    providers::{self, Format},
+    value::{Dict, Num, Tag, Value},
+    Figment, Profile, Provider,
+};
 
 pub fn data_default_profile_figment(figment: &Figment) -> Option<Dict> {
     // todo: support profile ?
@@ -11,61 +17,38 @@ pub fn data_default_profile_figment(figment: &Figment) -> Option<Dict> {
     }
 }
 
-pub fn json_values_eq_figment_value<'a, I>(json_values: I, fig: &figment::value::Value) -> bool
-where
-    I: Iterator<Item = &'a json::Value>,
-{
-    for json_value in json_values {
-        if match (json_value, fig) {
-            (json::Value::Null, figment::value::Value::Empty(_, _)) => true,
+/// Exact comparison of a JSON value with a figment value.
+/// Numbers are compared by value, whatever their type.
+pub fn json_value_eq_figment_value(json_value: &json::Value, fig: &Value) -> bool {
+    match (json_value, fig) {
+        (json::Value::Null, Value::Empty(_, _)) => true,
 
-            (json::Value::Bool(j_bool), figment::value::Value::Bool(_, f_bool)) => j_bool == f_bool,
+        (json::Value::Bool(j_bool), Value::Bool(_, f_bool)) => j_bool == f_bool,
 
-            (json::Value::Number(j_num), figment::value::Value::Num(_, f_num)) => {
-                j_num.as_i64() == f_num.to_u128_lossy().map(|e| e as i64)
-            }
+        (json::Value::Number(j_num), Value::Num(_, f_num)) => json_num_eq_figment_num(j_num, f_num),
 
-            (json::Value::String(j_str), figment::value::Value::String(_, f_str)) => j_str == f_str,
+        (json::Value::String(j_str), Value::String(_, f_str)) => j_str == f_str,
 
-            // Compare objects
-            (json::Value::Object(j_map), figment::value::Value::Dict(_, f_dict)) => {
-                j_map.len() == f_dict.len()
-                    && j_map.iter().all(|(k, j_val)| {
-                        f_dict.get(k).map_or(false, |f_val| {
-                            json_values_eq_figment_value(std::iter::once(j_val), f_val)
-                        })
-                    })
-            }
-
-            // If the types do not match, return false
-            _ => false,
-        } {
-            return true;
+        (json::Value::String(j_str), Value::Char(_, f_char)) => {
+            let mut chars = j_str.chars();
+            chars.next() == Some(*f_char) && chars.next().is_none()
         }
-    }
-
-    false
-}
-
-pub fn json_value_eq_figment_value(json_value: &json::Value, fig: &figment::value::Value) -> bool {
-    match (json_value, fig) {
-        (json::Value::Null, figment::value::Value::Empty(_, _)) => true,
 
-        (json::Value::Bool(j_bool), figment::value::Value::Bool(_, f_bool)) => j_bool == f_bool,
-
-        (json::Value::Number(j_num), figment::value::Value::Num(_, f_num)) => {
-            j_num.as_i64() == f_num.to_u128_lossy().map(|e| e as i64)
+        (json::Value::Array(j_vec), Value::Array(_, f_vec)) => {
+            j_vec.len() == f_vec.len()
+                && j_vec
+                    .iter()
+                    .zip(f_vec)
+                    .all(|(j_val, f_val)| json_value_eq_figment_value(j_val, f_val))
         }
 
-        (json::Value::String(j_str), figment::value::Value::String(_, f_str)) => j_str == f_str,
-
         // Compare objects
-        (json::Value::Object(j_map), figment::value::Value::Dict(_, f_dict)) => {
+        (json::Value::Object(j_map), Value::Dict(_, f_dict)) => {
             j_map.len() == f_dict.len()
                 && j_map.iter().all(|(k, j_val)| {
                     f_dict
                         .get(k)
-                        .map_or(false, |f_val| json_value_eq_figment_value(j_val, f_val))
+                        .is_some_and(|f_val| json_value_eq_figment_value(j_val, f_val))
                 })
         }
 
@@ -74,6 +57,72 @@ pub fn json_value_eq_figment_value(json_value: &json::Value, fig: &figment::valu
     }
 }
 
+fn json_num_eq_figment_num(j_num: &json::Number, f_num: &Num) -> bool {
+    enum Exact {
+        Int(i128),
+        /// Bigger than `i128::MAX`
+        BigUInt(u128),
+        F32(f32),
+        F64(f64),
+    }
+
+    let f_num = match *f_num {
+        Num::U8(x) => Exact::Int(x.into()),
+        Num::U16(x) => Exact::Int(x.into()),
+        Num::U32(x) => Exact::Int(x.into()),
+        Num::U64(x) => Exact::Int(x.into()),
+        Num::U128(x) => match i128::try_from(x) {
+            Ok(x) => Exact::Int(x),
+            Err(_) => Exact::BigUInt(x),
+        },
+        Num::USize(x) => Exact::Int(x as i128),
+        Num::I8(x) => Exact::Int(x.into()),
+        Num::I16(x) => Exact::Int(x.into()),
+        Num::I32(x) => Exact::Int(x.into()),
+        Num::I64(x) => Exact::Int(x.into()),
+        Num::I128(x) => Exact::Int(x),
+        Num::ISize(x) => Exact::Int(x as i128),
+        Num::F32(x) => Exact::F32(x),
+        Num::F64(x) => Exact::F64(x),
+    };
+
+    let j_int = j_num
+        .as_u64()
+        .map(i128::from)
+        .or_else(|| j_num.as_i64().map(i128::from));
+
+    // an integer is equal to a float without fractional part
+    fn int_eq_float(int: i128, float: f64) -> bool {
+        float.fract() == 0.0
+            && float >= i128::MIN as f64
+            && float < i128::MAX as f64
+            && float as i128 == int
+    }
+
+    match (j_int, f_num) {
+        (Some(j_int), Exact::Int(f_int)) => j_int == f_int,
+        (Some(_), Exact::BigUInt(_)) => false,
+        (Some(j_int), Exact::F32(f_float)) => int_eq_float(j_int, f_float.into()),
+        (Some(j_int), Exact::F64(f_float)) => int_eq_float(j_int, f_float),
+        (None, f_num) => {
+            let Some(j_float) = j_num.as_f64() else {
+                return false;
+            };
+
+            match f_num {
+                Exact::Int(f_int) => int_eq_float(f_int, j_float),
+                Exact::BigUInt(f_int) => j_float == f_int as f64 && j_float as u128 == f_int,
+                // the value was written for a f32: in full, or in its shortest form like `0.1`
+                Exact::F32(f_float) => {
+                    f64::from(f_float) == j_float
+                        || f_float.to_string().parse::<f64>() == Ok(j_float)
+                }
+                Exact::F64(f_float) => j_float == f_float,
+            }
+        }
+    }
+}
+
 pub fn figment_value_to_i128(value: &figment::value::Value) -> Option<i128> {
     type R = i128;
     match value.to_num()? {
@@ -113,3 +162,183 @@ pub fn figment_value_to_f64(value: &figment::value::Value) -> Option<f64> {
         figment::value::Num::F64(x) => Some(x as R),
     }
 }
+
+/// The f64 written like the f32: `0.1f32` is `0.1`, not `0.10000000149011612`
+pub fn f32_to_f64(float: f32) -> f64 {
+    float.to_string().parse().unwrap_or(f64::from(float))
+}
+
+/// Serialize a value so it can be pasted in a config file of this format.
+pub fn figment_value_to_string(value: &Value, format: &ConfigFormat) -> anyhow::Result<String> {
+    let str = match format {
+        ConfigFormat::Json | ConfigFormat::Jsonc | ConfigFormat::Json5 => {
+            json::to_string_pretty(value)?
+        }
+        ConfigFormat::CosmicRon | ConfigFormat::Ron => {
+            ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new())?
+        }
+        ConfigFormat::Toml => crate::providers::toml::figment_value_to_string(value)?,
+        ConfigFormat::Yaml => serde_yaml::to_string(value)?,
+        ConfigFormat::Kdl => crate::providers::kdl::figment_value_to_string(value)?,
+        ConfigFormat::Keyfile => crate::providers::keyfile::figment_value_to_string(value)?,
+    };
+
+    Ok(str)
+}
+
+/// Parse a value written in JSON, RON or TOML.
+pub fn figment_value_from_str(str: &str) -> anyhow::Result<Value> {
+    // an empty TOML document is valid
+    if str.trim().is_empty() {
+        bail!("no value");
+    }
+
+    // unlike serde_json, it reads the integers that don't fit in 64 bits exactly
+    let json_err = match crate::providers::jsonc::from_str(str) {
+        Ok(spanned) => return Ok(spanned.value),
+        Err(e) => e,
+    };
+
+    let ron_err = match crate::providers::ron_value::from_str(str) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    let toml_err = match toml_value_from_str(str) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+
+    Err(anyhow!(
+        "this is neither valid JSON ({json_err}), valid RON ({ron_err}) nor valid TOML ({toml_err})"
+    ))
+}
+
+/// A TOML document, or a single TOML value
+fn toml_value_from_str(str: &str) -> anyhow::Result<Value> {
+    let document_err = match providers::Toml::string(str).data() {
+        Ok(mut data) => {
+            let dict = data.remove(&Profile::Default).unwrap_or_default();
+            return Ok(Value::Dict(Tag::Default, dict));
+        }
+        Err(e) => e,
+    };
+
+    // a value alone is not a valid document
+    let mut data = providers::Toml::string(&format!("value = {str}"))
+        .data()
+        .map_err(|_| document_err)?;
+
+    data.remove(&Profile::Default)
+        .and_then(|mut dict| dict.remove("value"))
+        .ok_or_else(|| anyhow!("no value"))
+}
+
+#[cfg(test)]
+mod test {
+    use figment::value::{Num, Tag, Value};
+    use proptest::prelude::*;
+
+    use super::{figment_value_from_str, json_value_eq_figment_value};
+
+    fn num(num: Num) -> Value {
+        Value::Num(Tag::Default, num)
+    }
+
+    proptest! {
+        #[test]
+        fn test_eq_u64(x: u64) {
+            let json = json::json!(x);
+
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::U64(x))));
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::U128(x.into()))));
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::I128(x.into()))));
+            prop_assert!(!json_value_eq_figment_value(&json, &num(Num::U64(x.wrapping_add(1)))));
+        }
+
+        #[test]
+        fn test_eq_i64(x: i64) {
+            let json = json::json!(x);
+
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::I64(x))));
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::I128(x.into()))));
+            prop_assert_eq!(
+                json_value_eq_figment_value(&json, &num(Num::F64(x as f64))),
+                (x as f64) as i128 == x.into()
+            );
+            prop_assert!(!json_value_eq_figment_value(&json, &num(Num::I64(x.wrapping_sub(1)))));
+        }
+
+        #[test]
+        fn test_eq_f64(x in any::<f64>().prop_filter("finite", |x| x.is_finite()), y: f64) {
+            let json = json::json!(x);
+
+            prop_assert!(json_value_eq_figment_value(&json, &num(Num::F64(x))));
+            prop_assert_eq!(json_value_eq_figment_value(&json, &num(Num::F64(y))), x == y);
+        }
+
+        #[test]
+        fn test_eq_f32(x in any::<f32>().prop_filter("finite", |x| x.is_finite()), y: f64) {
+            let shortest = x.to_string().parse::<f64>().unwrap();
+
+            prop_assert!(json_value_eq_figment_value(&json::json!(f64::from(x)), &num(Num::F32(x))));
+            prop_assert!(json_value_eq_figment_value(&json::json!(shortest), &num(Num::F32(x))));
+            prop_assert_eq!(
+                json_value_eq_figment_value(&json::json!(y), &num(Num::F32(x))),
+                y == f64::from(x) || y == shortest
+            );
+        }
+
+        #[test]
+        fn test_eq_array(vec: Vec<i32>, other: Vec<i32>) {
+            let json = json::json!(vec);
+
+            let fig = |vec: &Vec<i32>| Value::Array(
+                Tag::Default,
+                vec.iter().map(|x| num(Num::I32(*x))).collect(),
+            );
+
+            prop_assert!(json_value_eq_figment_value(&json, &fig(&vec)));
+            prop_assert_eq!(json_value_eq_figment_value(&json, &fig(&other)), vec == other);
+        }
+    }
+
+    #[test]
+    fn test_eq_big_numbers() {
+        let json = json::json!(u64::MAX);
+
+        assert!(json_value_eq_figment_value(&json, &num(Num::U64(u64::MAX))));
+        assert!(!json_value_eq_figment_value(&json, &num(Num::I64(-1))));
+        assert!(!json_value_eq_figment_value(
+            &json::json!(0.5),
+            &num(Num::F64(0.25))
+        ));
+        assert!(json_value_eq_figment_value(
+            &json::json!(2.0),
+            &num(Num::U8(2))
+        ));
+        // the same f32, but not the same number
+        assert!(json_value_eq_figment_value(
+            &json::json!(0.1),
+            &num(Num::F32(0.1))
+        ));
+        assert!(!json_value_eq_figment_value(
+            &json::json!(0.100000001),
+            &num(Num::F32(0.1))
+        ));
+    }
+
+    #[test]
+    fn test_big_numbers_from_json() {
+        assert_eq!(
+            figment_value_from_str(&u128::MAX.to_string()).unwrap(),
+            num(Num::U128(u128::MAX))
+        );
+        assert_eq!(
+            figment_value_from_str(&format!("[{}]", i128::MIN)).unwrap(),
+            Value::Array(Tag::Default, vec![num(Num::I128(i128::MIN))])
+        );
+        assert_eq!(figment_value_from_str("1.5").unwrap(), num(Num::F64(1.5)));
+        assert!(figment_value_from_str(" \n").is_err());
+    }
+}
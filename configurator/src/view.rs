@@ -20,10 +20,10 @@ use crate::{
     message::{AppMsg, ChangeMsg, PageMsg},
     node::{
         data_path::{DataPath, DataPathType},
-        Node, NodeArray, NodeBool, NodeContainer, NodeEnum, NodeNumber, NodeObject, NodeString,
-        NodeValue,
+        Node, NodeAny, NodeArray, NodeBool, NodeChar, NodeContainer, NodeEnum, NodeNumber,
+        NodeObject, NodeOption, NodeString, NodeValue,
     },
-    page::Page,
+    page::{Page, WriteMode},
 };
 
 const SPACING: f32 = 10.;
@@ -31,15 +31,88 @@ const SPACING: f32 = 10.;
 pub fn view_app(app: &App) -> Element<'_, AppMsg> {
     let entity = app.nav_model.active();
 
-    match app.nav_model.data::<Page>(entity) {
-        Some(page) => {
-            container(view_page(entity, page).map(move |msg| AppMsg::PageMsg(entity, msg)))
-                .width(Length::Fill)
-                .height(Length::Fill)
-                .into()
+    let content = if !app.search.trim().is_empty() {
+        view_search_results(app)
+    } else {
+        match app.nav_model.data::<Page>(entity) {
+            Some(page) => view_page(entity, page).map(move |msg| AppMsg::PageMsg(entity, msg)),
+            None => text("no page selected").into(),
         }
-        None => text("no page selected").into(),
-    }
+    };
+
+    container(
+        column()
+            .push(view_search(app))
+            .push(content)
+            .spacing(SPACING),
+    )
+    .width(Length::Fill)
+    .height(Length::Fill)
+    .into()
+}
+
+fn view_search(app: &App) -> Element<'_, AppMsg> {
+    row()
+        .push(text_input("search", &app.search).on_input(AppMsg::Search))
+        .push(text("All pages"))
+        .push(toggler(app.search_all_pages).on_toggle(AppMsg::SearchAllPages))
+        .align_y(Alignment::Center)
+        .spacing(SPACING)
+        .into()
+}
+
+fn view_search_results(app: &App) -> Element<'_, AppMsg> {
+    let active = app.nav_model.active();
+
+    let results = app
+        .nav_model
+        .iter()
+        .filter(|entity| app.search_all_pages || *entity == active)
+        .filter_map(|entity| {
+            app.nav_model
+                .data::<Page>(entity)
+                .map(|page| (entity, page))
+        })
+        .flat_map(|(entity, page)| {
+            page.tree
+                .search(&app.search)
+                .into_iter()
+                .map(move |result| (entity, page, result))
+        })
+        .map(|(entity, page, result)| {
+            let path = result
+                .data_path
+                .iter()
+                .map(|component| component.to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            let path = if app.search_all_pages {
+                format!("{}: /{}", page.title(), path)
+            } else {
+                format!("/{}", path)
+            };
+
+            Element::from(
+                mouse_area(
+                    row()
+                        .push(text(path))
+                        .push(horizontal_space())
+                        .push(text(result.matched))
+                        .align_y(Alignment::Center),
+                )
+                .on_press(AppMsg::OpenSearchResult(entity, result.data_path)),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let content: Element<'_, AppMsg> = if results.is_empty() {
+        text("no result").into()
+    } else {
+        section().title("Results").extend(results).into()
+    };
+
+    scrollable(content).into()
 }
 
 fn view_data_path(data_path: &DataPath) -> Element<'_, PageMsg> {
@@ -76,24 +149,196 @@ fn view_page(entity: Entity, page: &Page) -> Element<'_, PageMsg> {
     let data_path = page.data_path.current();
 
     let node = page.tree.get_at(data_path.iter()).unwrap();
+    let default = page.tree.effective_default(data_path);
 
     let content = match &node.node {
-        Node::Bool(node_bool) => view_bool(data_path, node, node_bool),
-        Node::String(node_string) => view_string(data_path, node, node_string),
-        Node::Number(node_number) => view_number(data_path, node, node_number),
-        Node::Object(node_object) => view_object(data_path, node, node_object),
-        Node::Enum(node_enum) => view_enum(data_path, node, node_enum),
+        Node::Bool(node_bool) => view_bool(data_path, node, default, node_bool),
+        Node::String(node_string) => view_string(data_path, node, default, node_string),
+        Node::Char(node_char) => view_char(data_path, node, default, node_char),
+        Node::Number(node_number) => view_number(data_path, node, default, node_number),
+        Node::Object(node_object) => view_object(data_path, node, default, node_object),
+        Node::Enum(node_enum) => view_enum(data_path, node, default, node_enum),
+        Node::Option(node_option) => view_option(data_path, node, default, node_option),
         Node::Value(node_value) => view_value(data_path, node, node_value),
         Node::Null => text("null").into(),
-        Node::Array(node_array) => view_array(data_path, node, node_array),
-        Node::Any => todo!(),
+        Node::Array(node_array) => view_array(data_path, node, default, node_array),
+        Node::Any(node_any) => view_any(data_path, node, default, node_any),
     };
 
-    column()
-        .push(view_data_path(&page.data_path))
-        .push(scrollable(content))
-        .spacing(10)
-        .into()
+    let page_view = column()
+        .push(
+            row()
+                .push(view_data_path(&page.data_path))
+                .push(horizontal_space())
+                .push_maybe(if data_path.is_empty() {
+                    Some(
+                        row()
+                            .push(text("Only write overrides"))
+                            .push(toggler(page.write_mode == WriteMode::Overrides).on_toggle(
+                                |value| {
+                                    PageMsg::SetWriteMode(if value {
+                                        WriteMode::Overrides
+                                    } else {
+                                        WriteMode::Modified
+                                    })
+                                },
+                            ))
+                            .align_y(Alignment::Center)
+                            .spacing(SPACING),
+                    )
+                } else {
+                    None
+                })
+                .push(button::text("copy").on_press(PageMsg::Copy(data_path.to_vec())))
+                .push(button::text("paste").on_press(PageMsg::Paste(data_path.to_vec())))
+                .align_y(Alignment::Center),
+        )
+        .push(scrollable(
+            column()
+                .push_maybe(if data_path.is_empty() {
+                    view_version_mismatch(page)
+                } else {
+                    None
+                })
+                .push_maybe(if data_path.is_empty() {
+                    view_load_error(page)
+                } else {
+                    None
+                })
+                .push_maybe(view_file_errors(data_path, page))
+                .push(content)
+                .push_maybe(view_default_source(data_path, default))
+                .push_maybe(if data_path.is_empty() {
+                    view_backups(page)
+                } else {
+                    None
+                })
+                .spacing(SPACING),
+        ))
+        .spacing(10);
+
+    // a drag released outside of a drop target is cancelled
+    mouse_area(page_view).on_release(PageMsg::DragEnd).into()
+}
+
+/// Warn when the config on disk is not the version of the schema
+fn view_version_mismatch(page: &Page) -> Option<Element<'_, PageMsg>> {
+    let version = page.version?;
+    let disk_version = page.disk_version?;
+
+    if version == disk_version {
+        return None;
+    }
+
+    Some(
+        section()
+            .title("Version mismatch")
+            .add(text(format!(
+                "The config on disk is the version {disk_version}, but this schema describes \
+                the version {version}. Saving writes the values read to the version {version}, \
+                and leaves the version {disk_version} untouched."
+            )))
+            .into(),
+    )
+}
+
+/// The config could only be partly loaded, so it is not saved
+fn view_load_error(page: &Page) -> Option<Element<'_, PageMsg>> {
+    let err = page.load_error.as_ref()?;
+
+    Some(
+        section()
+            .title("Could not load")
+            .add(text(
+                "Some values were not loaded. The changes are not saved until the config is fixed.",
+            ))
+            .add(text(err.as_str()))
+            .into(),
+    )
+}
+
+/// The files of the config that could not be parsed: all of them at the root,
+/// otherwise the one of the current key
+fn view_file_errors<'a>(
+    data_path: &[DataPathType],
+    page: &'a Page,
+) -> Option<Element<'a, PageMsg>> {
+    let errors = page
+        .file_errors
+        .iter()
+        .filter(|error| match data_path.first() {
+            Some(DataPathType::Name(key)) => &error.key == key,
+            Some(DataPathType::Indice(_)) => false,
+            None => true,
+        })
+        .collect::<Vec<_>>();
+
+    if errors.is_empty() {
+        return None;
+    }
+
+    Some(
+        section()
+            .title("Could not parse")
+            .add(text(
+                "The values of these files are ignored. Saving a new value replaces the file.",
+            ))
+            .extend(errors.into_iter().map(|error| text(error.to_string())))
+            .into(),
+    )
+}
+
+fn view_backups(page: &Page) -> Option<Element<'_, PageMsg>> {
+    if page.backups.is_empty() {
+        return None;
+    }
+
+    Some(
+        section()
+            .title("Backups")
+            .add(text(
+                "The config is saved before the first change of each session. \
+                Restoring a backup saves the current config first.",
+            ))
+            .extend(page.backups.iter().map(|backup| {
+                row()
+                    .push(text(backup.date_string()))
+                    .push(horizontal_space())
+                    .push(
+                        button::text("restore")
+                            .on_press(PageMsg::RestoreBackup(backup.path.clone())),
+                    )
+                    .align_y(Alignment::Center)
+                    .spacing(SPACING)
+            }))
+            .into(),
+    )
+}
+
+/// Explain why the default shown is not the one of the node
+fn view_default_source<'a>(
+    data_path: &[DataPathType],
+    default: Option<EffectiveDefault<'a>>,
+) -> Option<Element<'a, PageMsg>> {
+    let DefaultSource::Ancestor(len) = default?.source else {
+        return None;
+    };
+
+    let path = data_path[..len]
+        .iter()
+        .map(|component| component.to_string())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    Some(
+        section()
+            .title("Default source")
+            .add(text(format!(
+                "/{path} is not in the config, so its default applies here. \
+                The default of this value is only used once /{path} is written."
+            )))
+            .into(),
+    )
 }
 
 fn no_value_defined_warning_icon<'a, M: 'a>() -> Element<'a, M> {
@@ -116,8 +361,11 @@ fn this_will_remove_all_children<'a, M: 'a>() -> Element<'a, M> {
     .into()
 }
 
+/// `pos` is the position of the value in the current node, which contains `len` values
 fn node_list<'a>(
     name: DataPathType,
+    pos: usize,
+    len: usize,
     inner_node: &'a NodeContainer,
     data_path: &'a [DataPathType],
 ) -> Element<'a, PageMsg> {
@@ -133,6 +381,11 @@ fn node_list<'a>(
     mouse_area(
         row()
             .align_y(Alignment::Center)
+            .push_maybe(if inner_node.removable {
+                Some(mouse_area(icon!("drag_indicator24")).on_press(PageMsg::DragStart(pos)))
+            } else {
+                None
+            })
             .push(text(format!("{}", name)))
             .push_maybe(
                 if inner_node.removable
@@ -159,7 +412,19 @@ fn node_list<'a>(
                         })
                         .into(),
                 ),
-
+                Node::Option(node_option) => Some(
+                    toggler(node_option.enabled.unwrap_or_default())
+                        .on_toggle(move |value| {
+                            PageMsg::ChangeMsg(
+                                append_data_path(data_path, &name),
+                                ChangeMsg::ChangeOption(value),
+                            )
+                        })
+                        .into(),
+                ),
+                Node::Array(node_array) => {
+                    tuple_row(append_data_path(data_path, &name), node_array)
+                }
                 Node::Enum(node_enum) => {
                     #[derive(Eq, Clone)]
                     struct Key<'a> {
@@ -223,21 +488,49 @@ fn node_list<'a>(
                 None
             })
             .push_maybe(if inner_node.removable {
-                Some(icon_button!("close24").on_press(PageMsg::ChangeMsg(
-                    data_path.to_vec(),
-                    ChangeMsg::Remove(name_cloned.clone()),
-                )))
+                Some(
+                    row()
+                        .push(icon_button!("arrow_upward24").on_press_maybe(
+                            pos.checked_sub(1).map(|to| {
+                                PageMsg::ChangeMsg(
+                                    data_path.to_vec(),
+                                    ChangeMsg::Move { from: pos, to },
+                                )
+                            }),
+                        ))
+                        .push(icon_button!("arrow_downward24").on_press_maybe(
+                            (pos + 1 < len).then(|| {
+                                PageMsg::ChangeMsg(
+                                    data_path.to_vec(),
+                                    ChangeMsg::Move {
+                                        from: pos,
+                                        to: pos + 1,
+                                    },
+                                )
+                            }),
+                        ))
+                        .push(icon_button!("content_copy24").on_press(PageMsg::ChangeMsg(
+                            data_path.to_vec(),
+                            ChangeMsg::Duplicate(name_cloned.clone()),
+                        )))
+                        .push(icon_button!("close24").on_press(PageMsg::ChangeMsg(
+                            data_path.to_vec(),
+                            ChangeMsg::Remove(name_cloned.clone()),
+                        ))),
+                )
             } else {
                 None
             }),
     )
     .on_press(PageMsg::OpenDataPath(name_cloned))
+    .on_release(PageMsg::Drop(pos))
     .into()
 }
 
 fn view_object<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_object: &'a NodeObject,
 ) -> Element<'a, PageMsg> {
     column()
@@ -249,14 +542,57 @@ fn view_object<'a>(
         .push(
             section()
                 .title("Values")
-                .extend(node_object.nodes.iter().map(|(name, inner_node)| {
-                    node_list(DataPathType::Name(name.clone()), inner_node, data_path)
-                })),
+                .extend(
+                    node_object
+                        .nodes
+                        .iter()
+                        .enumerate()
+                        .map(|(pos, (name, inner_node))| {
+                            node_list(
+                                DataPathType::Name(name.clone()),
+                                pos,
+                                node_object.nodes.len(),
+                                inner_node,
+                                data_path,
+                            )
+                        }),
+                ),
         )
         .push_maybe(node_object.template.as_ref().map(|_| {
             icon_button!("add24").on_press(PageMsg::DialogAddNewNodeToObject(data_path.to_vec()))
         }))
-        .push_maybe(node.default.as_ref().map(|default| {
+        .push_maybe(if node_object.unknown.is_empty() {
+            None
+        } else {
+            Some(
+                section()
+                    .title("Unrecognized settings")
+                    .add(text(
+                        "These settings are not described by the schema. They are kept as is.",
+                    ))
+                    .extend(node_object.unknown.iter().map(|(key, value)| {
+                        row()
+                            .push(text(key))
+                            .push(horizontal_space())
+                            .push(text(json::to_string(value).unwrap_or_default()))
+                            .push(icon_button!("close24").on_press(PageMsg::ChangeMsg(
+                                data_path.to_vec(),
+                                ChangeMsg::RemoveUnknown(Some(key.clone())),
+                            )))
+                            .align_y(Alignment::Center)
+                            .spacing(SPACING)
+                    }))
+                    .add(
+                        row()
+                            .push(horizontal_space())
+                            .push(button::text("remove all").on_press(PageMsg::ChangeMsg(
+                                data_path.to_vec(),
+                                ChangeMsg::RemoveUnknown(None),
+                            ))),
+                    ),
+            )
+        })
+        .push_maybe(default.map(|EffectiveDefault { value: default, .. }| {
             section().title("Default").add(
                 row()
                     .push(horizontal_space())
@@ -277,32 +613,43 @@ fn view_object<'a>(
 fn view_array<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_array: &'a NodeArray,
 ) -> Element<'a, PageMsg> {
+    let values = node_array
+        .values
+        .as_ref()
+        .map_or(&[] as &[NodeContainer], |v| v.as_slice());
+
+    let values_section = match tuple_row(data_path.to_vec(), node_array) {
+        Some(tuple_row) => section().title("Values").add(tuple_row),
+        None => section()
+            .title("Values")
+            .extend(values.iter().enumerate().map(|(pos, inner_node)| {
+                node_list(
+                    DataPathType::Indice(pos),
+                    pos,
+                    values.len(),
+                    inner_node,
+                    data_path,
+                )
+            })),
+    };
+
     column()
         .push_maybe(
             node.desc
                 .as_ref()
                 .map(|desc| section().title("Description").add(text(desc))),
         )
-        .push(
-            section().title("Values").extend(
-                node_array
-                    .values
-                    .as_ref()
-                    .map_or(&[] as &[NodeContainer], |v| v.as_slice())
-                    .iter()
-                    .enumerate()
-                    .map(|(pos, inner_node)| {
-                        node_list(DataPathType::Indice(pos), inner_node, data_path)
-                    }),
-            ),
-        )
-        .push(icon_button!("add24").on_press(PageMsg::ChangeMsg(
-            data_path.to_vec(),
-            ChangeMsg::AddNewNodeToArray,
-        )))
-        .push_maybe(node.default.as_ref().map(|default| {
+        .push(values_section)
+        .push_maybe(node_array.can_add().then(|| {
+            icon_button!("add24").on_press(PageMsg::ChangeMsg(
+                data_path.to_vec(),
+                ChangeMsg::AddNewNodeToArray,
+            ))
+        }))
+        .push_maybe(default.map(|EffectiveDefault { value: default, .. }| {
             section().title("Default").add(
                 row()
                     .push(horizontal_space())
@@ -323,6 +670,7 @@ fn view_array<'a>(
 fn view_enum<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_enum: &'a NodeEnum,
 ) -> Element<'a, PageMsg> {
     column()
@@ -370,7 +718,7 @@ fn view_enum<'a>(
                     .padding(5)
                 })),
         )
-        .push_maybe(node.default.as_ref().map(|default| {
+        .push_maybe(default.map(|EffectiveDefault { value: default, .. }| {
             section()
                 .title("Default")
                 .add_maybe(default.clone().into_string().map(|default| {
@@ -399,9 +747,71 @@ fn view_enum<'a>(
         .into()
 }
 
+fn view_option<'a>(
+    data_path: &'a [DataPathType],
+    node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
+    node_option: &'a NodeOption,
+) -> Element<'a, PageMsg> {
+    let enabled = node_option.enabled.unwrap_or_default();
+
+    column()
+        .push_maybe(
+            node.desc
+                .as_ref()
+                .map(|desc| section().title("Description").add(text(desc))),
+        )
+        .push(
+            section().title("Value").add(
+                row()
+                    .push(text("Enabled"))
+                    .push(horizontal_space())
+                    .push_maybe(if enabled {
+                        Some(
+                            button::text("modify")
+                                .on_press(PageMsg::OpenDataPath(DataPathType::Indice(0))),
+                        )
+                    } else {
+                        None
+                    })
+                    .push_maybe(
+                        if node_option.enabled.is_none()
+                            || (enabled && !node_option.inner.is_valid())
+                        {
+                            Some(no_value_defined_warning_icon())
+                        } else {
+                            None
+                        },
+                    )
+                    .push(toggler(enabled).on_toggle(move |value| {
+                        PageMsg::ChangeMsg(data_path.to_vec(), ChangeMsg::ChangeOption(value))
+                    }))
+                    .align_y(Alignment::Center)
+                    .spacing(SPACING),
+            ),
+        )
+        .push_maybe(default.map(|EffectiveDefault { value: default, .. }| {
+            section().title("Default").add(
+                row()
+                    .push(horizontal_space())
+                    .push(
+                        // xxx: the on_press need to be lazy
+                        button::text("reset to default").on_press(PageMsg::ChangeMsg(
+                            data_path.to_vec(),
+                            ChangeMsg::ApplyDefault,
+                        )),
+                    )
+                    .push(this_will_remove_all_children()),
+            )
+        }))
+        .spacing(SPACING)
+        .into()
+}
+
 fn view_bool<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_bool: &'a NodeBool,
 ) -> Element<'a, PageMsg> {
     column()
@@ -428,8 +838,8 @@ fn view_bool<'a>(
             ),
         )
         .push_maybe(
-            node.default
-                .as_ref()
+            default
+                .map(|default| default.value)
                 .and_then(|v| v.to_bool())
                 .map(|default| {
                     section()
@@ -453,9 +863,127 @@ fn view_bool<'a>(
         .into()
 }
 
+fn view_char<'a>(
+    data_path: &'a [DataPathType],
+    node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
+    node_char: &'a NodeChar,
+) -> Element<'a, PageMsg> {
+    column()
+        .push_maybe(
+            node.desc
+                .as_ref()
+                .map(|desc| section().title("Description").add(text(desc))),
+        )
+        .push(
+            section().title("Value").add(
+                row()
+                    .push(text("Current value"))
+                    .push(horizontal_space())
+                    .push(char_input(data_path.to_vec(), node_char))
+                    .push_maybe(if node_char.value.is_none() {
+                        Some(no_value_defined_warning_icon())
+                    } else {
+                        None
+                    }),
+            ),
+        )
+        .push_maybe(
+            default
+                .map(|default| default.value)
+                .and_then(|v| v.as_str())
+                .map(|default| {
+                    section()
+                        .title("Default")
+                        .add(
+                            row()
+                                .push(text("Default value"))
+                                .push(horizontal_space())
+                                .push(text(default)),
+                        )
+                        .add(row().push(horizontal_space()).push(
+                            // xxx: the on_press need to be lazy
+                            button::text("reset to default").on_press(PageMsg::ChangeMsg(
+                                data_path.to_vec(),
+                                ChangeMsg::ApplyDefault,
+                            )),
+                        ))
+                }),
+        )
+        .spacing(SPACING)
+        .into()
+}
+
+/// The last typed character replaces the current one
+fn char_input<'a>(data_path: Vec<DataPathType>, node_char: &NodeChar) -> Element<'a, PageMsg> {
+    text_input(
+        "char",
+        node_char.value.map(String::from).unwrap_or_default(),
+    )
+    .on_input(move |value| match value.chars().last() {
+        Some(value) => PageMsg::ChangeMsg(data_path.clone(), ChangeMsg::ChangeChar(value)),
+        None => PageMsg::None,
+    })
+    .into()
+}
+
+/// Inputs of a tuple or a fixed-size array, on a single row.
+/// `None` if one of its values can't be edited inline.
+fn tuple_row<'a>(
+    data_path: Vec<DataPathType>,
+    node_array: &'a NodeArray,
+) -> Option<Element<'a, PageMsg>> {
+    if !node_array.is_fixed_len() {
+        return None;
+    }
+
+    let inputs = node_array
+        .values
+        .as_ref()?
+        .iter()
+        .enumerate()
+        .map(|(pos, node)| {
+            let mut data_path = data_path.clone();
+            data_path.push(DataPathType::Indice(pos));
+
+            let input: Element<'a, PageMsg> = match &node.node {
+                Node::Bool(node_bool) => toggler(node_bool.value.unwrap_or_default())
+                    .on_toggle(move |value| {
+                        PageMsg::ChangeMsg(data_path.clone(), ChangeMsg::ChangeBool(value))
+                    })
+                    .into(),
+                Node::String(node_string) => {
+                    text_input("value", node_string.value.as_deref().unwrap_or_default())
+                        .on_input(move |value| {
+                            PageMsg::ChangeMsg(data_path.clone(), ChangeMsg::ChangeString(value))
+                        })
+                        .into()
+                }
+                Node::Char(node_char) => char_input(data_path, node_char),
+                Node::Number(node_number) => text_input("value", &node_number.value_string)
+                    .on_input(move |value| {
+                        PageMsg::ChangeMsg(data_path.clone(), ChangeMsg::ChangeNumber(value))
+                    })
+                    .into(),
+                _ => return None,
+            };
+
+            Some(input)
+        })
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(
+        row::with_children(inputs)
+            .spacing(SPACING)
+            .align_y(Alignment::Center)
+            .into(),
+    )
+}
+
 fn view_string<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_string: &'a NodeString,
 ) -> Element<'a, PageMsg> {
     column()
@@ -487,8 +1015,8 @@ fn view_string<'a>(
             ),
         )
         .push_maybe(
-            node.default
-                .as_ref()
+            default
+                .map(|default| default.value)
                 .and_then(|v| v.as_str())
                 .map(|default| {
                     section()
@@ -515,6 +1043,7 @@ fn view_string<'a>(
 fn view_number<'a>(
     data_path: &'a [DataPathType],
     node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
     node_number: &'a NodeNumber,
 ) -> Element<'a, PageMsg> {
     column()
@@ -524,37 +1053,65 @@ fn view_number<'a>(
                 .map(|desc| section().title("Description").add(text(desc))),
         )
         .push(
-            section().title("Value").add(
-                row()
-                    .push(text("Current value"))
-                    .push(horizontal_space())
-                    .push(
-                        text_input("value", &node_number.value_string).on_input(move |value| {
-                            PageMsg::ChangeMsg(data_path.to_vec(), ChangeMsg::ChangeNumber(value))
-                        }),
-                    )
-                    .push_maybe(if node_number.value.is_none() {
-                        Some(no_value_defined_warning_icon())
-                    } else if node_number
-                        .try_parse_from_str(&node_number.value_string)
-                        .is_err()
-                    {
-                        Some(
-                            tooltip(
-                                icon!("report24"),
-                                text("This value is incorrect."),
-                                Position::Top,
+            section()
+                .title("Value")
+                .add(
+                    row()
+                        .push(text("Current value"))
+                        .push(horizontal_space())
+                        .push(text_input("value", &node_number.value_string).on_input(
+                            move |value| {
+                                PageMsg::ChangeMsg(
+                                    data_path.to_vec(),
+                                    ChangeMsg::ChangeNumber(value),
+                                )
+                            },
+                        ))
+                        .push_maybe(if node_number.value.is_none() {
+                            Some(no_value_defined_warning_icon())
+                        } else if node_number
+                            .try_parse_from_str(&node_number.value_string)
+                            .is_err()
+                        {
+                            Some(
+                                tooltip(
+                                    icon!("report24"),
+                                    text("This value is incorrect."),
+                                    Position::Top,
+                                )
+                                .into(),
                             )
-                            .into(),
-                        )
-                    } else {
-                        None
-                    }),
-            ),
+                        } else {
+                            None
+                        }),
+                )
+                .extend(
+                    // only floats can have a shorter representation
+                    node_number
+                        .value
+                        .as_ref()
+                        .and_then(|value| {
+                            let exact = value.to_string_exact();
+                            (exact != value.to_string()).then_some(exact)
+                        })
+                        .map(|exact| {
+                            row()
+                                .push(text("Full precision"))
+                                .push(horizontal_space())
+                                .push(toggler(node_number.value_string == exact).on_toggle(
+                                    move |value| {
+                                        PageMsg::ChangeMsg(
+                                            data_path.to_vec(),
+                                            ChangeMsg::NumberFullPrecision(value),
+                                        )
+                                    },
+                                ))
+                        }),
+                ),
         )
         .push_maybe(
-            node.default
-                .as_ref()
+            default
+                .map(|default| default.value)
                 .and_then(|v| v.to_num())
                 .and_then(|v| node_number.try_from_figment_num(v).ok())
                 .map(|default| {
@@ -598,3 +1155,62 @@ fn view_value<'a>(
         .spacing(SPACING)
         .into()
 }
+
+fn view_any<'a>(
+    data_path: &'a [DataPathType],
+    node: &'a NodeContainer,
+    default: Option<EffectiveDefault<'a>>,
+    node_any: &'a NodeAny,
+) -> Element<'a, PageMsg> {
+    column()
+        .push_maybe(
+            node.desc
+                .as_ref()
+                .map(|desc| section().title("Description").add(text(desc))),
+        )
+        .push(
+            section().title("Value").add(
+                row()
+                    .push(text("Current value"))
+                    .push(horizontal_space())
+                    .push(
+                        text_input("JSON or RON value", &node_any.value_string).on_input(
+                            move |value| {
+                                PageMsg::ChangeMsg(data_path.to_vec(), ChangeMsg::ChangeAny(value))
+                            },
+                        ),
+                    )
+                    .push_maybe(if node_any.value.is_none() {
+                        Some(no_value_defined_warning_icon())
+                    } else {
+                        None
+                    }),
+            ),
+        )
+        .push_maybe(node_any.error.as_ref().map(|err| {
+            row()
+                .push(icon!("report24"))
+                .push(text(err))
+                .align_y(Alignment::Center)
+                .spacing(SPACING)
+        }))
+        .push_maybe(default.map(|EffectiveDefault { value: default, .. }| {
+            section()
+                .title("Default")
+                .add(
+                    row()
+                        .push(text("Default value"))
+                        .push(horizontal_space())
+                        .push(text(json::to_string(default).unwrap_or_default())),
+                )
+                .add(row().push(horizontal_space()).push(
+                    // xxx: the on_press need to be lazy
+                    button::text("reset to default").on_press(PageMsg::ChangeMsg(
+                        data_path.to_vec(),
+                        ChangeMsg::ApplyDefault,
+                    )),
+                ))
+        }))
+        .spacing(SPACING)
+        .into()
+}
@@ -10,6 +10,7 @@ pub fn gen_schema<S: JsonSchema>(
     source_home_path: Option<&str>,
     write_path: Option<&str>,
     format: Option<ConfigFormat>,
+    version: Option<u64>,
 ) -> Result<String, Box<dyn std::error::Error>> {
     let schema = schema_for!(S);
 
@@ -45,6 +46,10 @@ pub fn gen_schema<S: JsonSchema>(
         );
     }
 
+    if let Some(version) = version {
+        obj.insert("X_CONFIGURATOR_VERSION".into(), Value::from(version));
+    }
+
     let str = json::to_string_pretty(&value)?;
     Ok(str)
 }
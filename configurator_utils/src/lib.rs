@@ -6,6 +6,13 @@ use anyhow::anyhow;
 pub enum ConfigFormat {
     Json,
     CosmicRon,
+    Toml,
+    Yaml,
+    Kdl,
+    Keyfile,
+    Ron,
+    Jsonc,
+    Json5,
 }
 
 impl Display for ConfigFormat {
@@ -13,6 +20,13 @@ impl Display for ConfigFormat {
         match self {
             ConfigFormat::Json => write!(f, "json"),
             ConfigFormat::CosmicRon => write!(f, "cosmic_ron"),
+            ConfigFormat::Toml => write!(f, "toml"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+            ConfigFormat::Kdl => write!(f, "kdl"),
+            ConfigFormat::Keyfile => write!(f, "keyfile"),
+            ConfigFormat::Ron => write!(f, "ron"),
+            ConfigFormat::Jsonc => write!(f, "jsonc"),
+            ConfigFormat::Json5 => write!(f, "json5"),
         }
     }
 }
@@ -24,6 +38,14 @@ impl TryFrom<&str> for ConfigFormat {
         let format = match value {
             "json" => ConfigFormat::Json,
             "cosmic_ron" => ConfigFormat::CosmicRon,
+            "toml" => ConfigFormat::Toml,
+            "yaml" => ConfigFormat::Yaml,
+            "kdl" => ConfigFormat::Kdl,
+            // also guessed from the extension of the file
+            "keyfile" | "ini" | "desktop" => ConfigFormat::Keyfile,
+            "ron" => ConfigFormat::Ron,
+            "jsonc" => ConfigFormat::Jsonc,
+            "json5" => ConfigFormat::Json5,
             _ => Err(anyhow!("unknown format: {}", value))?,
         };
         Ok(format)